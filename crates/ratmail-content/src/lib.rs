@@ -6,7 +6,7 @@ use base64::engine::general_purpose::STANDARD as BASE64_STD;
 use linkify::{LinkFinder, LinkKind};
 use mailparse::{MailHeaderMap, ParsedMail};
 
-use ratmail_core::{AttachmentMeta, LinkInfo};
+use ratmail_core::{AttachmentMeta, LinkInfo, OMITTED_PART_HEADER};
 
 #[derive(Debug, Clone)]
 pub struct DisplayText {
@@ -25,6 +25,9 @@ pub struct AttachmentData {
     pub filename: String,
     pub mime: String,
     pub data: Vec<u8>,
+    /// IMAP section of an attachment that has not been downloaded yet;
+    /// `data` is empty until it is.
+    pub omitted_section: Option<String>,
 }
 
 pub fn extract_display(raw: &[u8], width_cols: usize) -> Result<DisplayText> {
@@ -157,12 +160,15 @@ fn collect_attachments(parsed: &ParsedMail, out: &mut Vec<AttachmentMeta>) -> Re
         ) || filename.is_some();
 
         if is_attachment {
-            let body = parsed.get_body_raw()?;
+            let size = match omitted_part(parsed) {
+                Some((_, size)) => size,
+                None => parsed.get_body_raw()?.len(),
+            };
             let name = filename.take().unwrap_or_else(|| "attachment".to_string());
             out.push(AttachmentMeta {
                 filename: name,
                 mime: ctype,
-                size: body.len(),
+                size,
             });
         }
         return Ok(());
@@ -201,6 +207,7 @@ fn collect_attachment_at(
                     filename: name,
                     mime: ctype,
                     data: body,
+                    omitted_section: omitted_part(parsed).map(|(section, _)| section),
                 }));
             }
             *current += 1;
@@ -216,6 +223,21 @@ fn collect_attachment_at(
     Ok(None)
 }
 
+/// Section and decoded size of a part left out of a partially fetched
+/// message.
+fn omitted_part(parsed: &ParsedMail) -> Option<(String, usize)> {
+    let value = parsed.headers.get_first_value(OMITTED_PART_HEADER)?;
+    let (section, size) = value.split_once(';')?;
+    let size: usize = size.trim().strip_prefix("size=")?.parse().ok()?;
+    let base64 = parsed
+        .headers
+        .get_first_value("Content-Transfer-Encoding")
+        .is_some_and(|cte| cte.trim().eq_ignore_ascii_case("base64"));
+    // The server reports the encoded size.
+    let size = if base64 { size / 4 * 3 } else { size };
+    Some((section.trim().to_string(), size))
+}
+
 fn find_html_part(parsed: &ParsedMail) -> Result<Option<String>> {
     let mut html: Option<String> = None;
     walk_parts(parsed, &mut |part| {
@@ -763,4 +785,33 @@ mod tests {
             "https://drive.google.com/drive/folders/abcd1234?usp=sharing"
         );
     }
+
+    #[test]
+    fn omitted_attachments_report_server_size_and_section() {
+        let raw = concat!(
+            "Subject: Report\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See attached.\r\n",
+            "--b\r\n",
+            "Content-Type: application/pdf; name=\"report.pdf\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "X-Ratmail-Omitted: 2; size=4000\r\n",
+            "\r\n",
+            "\r\n",
+            "--b--\r\n",
+        )
+        .as_bytes();
+        let attachments = extract_attachments(raw).unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].filename, "report.pdf");
+        assert_eq!(attachments[0].size, 3000);
+
+        let data = extract_attachment_data(raw, 0).unwrap().unwrap();
+        assert_eq!(data.omitted_section.as_deref(), Some("2"));
+        assert!(data.data.is_empty());
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::{Mutex, OnceLock};
@@ -7,7 +7,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::Local;
-use mailparse::dateparse;
+use mailparse::{MailHeaderMap, dateparse};
 use serde::{Deserialize, Serialize};
 use sqlx::{SqlitePool, sqlite::SqliteConnectOptions, sqlite::SqlitePoolOptions};
 
@@ -24,6 +24,72 @@ pub struct Folder {
     pub account_id: i64,
     pub name: String,
     pub unread: u32,
+    #[serde(default)]
+    pub role: Option<FolderRole>,
+}
+
+/// RFC 6154 special-use role of a folder. At most one folder per account
+/// carries each role; features look folders up by role instead of by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FolderRole {
+    All,
+    Archive,
+    Drafts,
+    Flagged,
+    Junk,
+    Sent,
+    Trash,
+}
+
+impl FolderRole {
+    pub const ALL: [FolderRole; 7] = [
+        FolderRole::All,
+        FolderRole::Archive,
+        FolderRole::Drafts,
+        FolderRole::Flagged,
+        FolderRole::Junk,
+        FolderRole::Sent,
+        FolderRole::Trash,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FolderRole::All => "all",
+            FolderRole::Archive => "archive",
+            FolderRole::Drafts => "drafts",
+            FolderRole::Flagged => "flagged",
+            FolderRole::Junk => "junk",
+            FolderRole::Sent => "sent",
+            FolderRole::Trash => "trash",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "all" => Some(FolderRole::All),
+            "archive" => Some(FolderRole::Archive),
+            "drafts" => Some(FolderRole::Drafts),
+            "flagged" => Some(FolderRole::Flagged),
+            "junk" | "spam" => Some(FolderRole::Junk),
+            "sent" => Some(FolderRole::Sent),
+            "trash" => Some(FolderRole::Trash),
+            _ => None,
+        }
+    }
+
+    /// Name used when ratmail has to create the folder locally.
+    pub fn default_name(self) -> &'static str {
+        match self {
+            FolderRole::All => "All Mail",
+            FolderRole::Archive => "Archive",
+            FolderRole::Drafts => "Drafts",
+            FolderRole::Flagged => "Starred",
+            FolderRole::Junk => "Spam",
+            FolderRole::Sent => "Sent",
+            FolderRole::Trash => "Trash",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +100,26 @@ pub struct FolderSyncState {
     pub last_seen_uid: Option<i64>,
     pub last_sync_ts: Option<i64>,
     pub oldest_ts: Option<i64>,
+    pub highest_modseq: Option<i64>,
+}
+
+/// A server change that has not been confirmed yet. `payload` is the JSON of
+/// the mail command; the store does not interpret it.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PendingOp {
+    pub id: i64,
+    pub account_id: i64,
+    pub kind: String,
+    pub payload: String,
+    pub created_ts: i64,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub last_attempt_ts: Option<i64>,
+    /// The server refused it; it stays for the user to retry or drop but no
+    /// longer holds up the operations queued after it.
+    pub failed: bool,
+    /// Local draft holding the message of a queued send until it goes out.
+    pub draft_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,7 +131,36 @@ pub struct MessageSummary {
     pub from: String,
     pub subject: String,
     pub unread: bool,
+    #[serde(default)]
+    pub flagged: bool,
+    /// IMAP keywords (user-defined flags such as `$label1` or `work`).
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Gmail X-GM-MSGID; copies of a message in several label folders share it.
+    #[serde(default)]
+    pub gmail_msgid: Option<u64>,
+    /// Gmail X-GM-THRID conversation id.
+    #[serde(default)]
+    pub gmail_thread_id: Option<u64>,
     pub preview: String,
+    /// Raw To and Cc headers, so recipient search works without the body.
+    #[serde(default)]
+    pub to: String,
+    #[serde(default)]
+    pub cc: String,
+    #[serde(default)]
+    pub message_id_header: String,
+    #[serde(default)]
+    pub in_reply_to: String,
+    #[serde(default)]
+    pub references: String,
+    #[serde(default)]
+    pub reply_to: String,
+    #[serde(default)]
+    pub list_id: String,
+    /// RFC822.SIZE in bytes; `None` until the server reported it.
+    #[serde(default)]
+    pub size: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -91,6 +206,9 @@ pub struct TileMeta {
 }
 
 pub const DEFAULT_TEXT_WIDTH: i64 = 80;
+/// Header on a MIME part whose body was left out of a partially fetched
+/// message, e.g. `X-Ratmail-Omitted: 2.1; size=26214400`.
+pub const OMITTED_PART_HEADER: &str = "X-Ratmail-Omitted";
 static LOG_FILE: OnceLock<Mutex<Option<std::fs::File>>> = OnceLock::new();
 
 pub fn log_debug(msg: &str) {
@@ -139,6 +257,15 @@ fn draft_preview(body: &str) -> String {
     first.trim().chars().take(200).collect()
 }
 
+fn first_text_body(mail: &mailparse::ParsedMail) -> Option<String> {
+    if mail.subparts.is_empty() {
+        return (mail.ctype.mimetype == "text/plain")
+            .then(|| mail.get_body().ok())
+            .flatten();
+    }
+    mail.subparts.iter().find_map(first_text_body)
+}
+
 fn draft_raw(
     from_addr: &str,
     to: &str,
@@ -174,6 +301,15 @@ pub trait MailStore: Send + Sync {
     async fn load_snapshot(&self, account_id: i64, folder_id: i64) -> Result<StoreSnapshot>;
     async fn get_raw_body(&self, message_id: i64) -> Result<Option<Vec<u8>>>;
     async fn upsert_raw_body(&self, message_id: i64, raw: &[u8]) -> Result<()>;
+    /// IMAP sections left out of the cached body; empty when it is complete.
+    async fn get_missing_parts(&self, message_id: i64) -> Result<Vec<String>>;
+    /// Stores a body that lacks the parts in `missing_parts`.
+    async fn upsert_partial_body(
+        &self,
+        message_id: i64,
+        raw: &[u8],
+        missing_parts: &[String],
+    ) -> Result<()>;
     async fn upsert_cache_text(&self, message_id: i64, width_cols: i64, text: &str) -> Result<()>;
     async fn get_cache_html(&self, message_id: i64, remote_policy: &str) -> Result<Option<String>>;
     async fn upsert_cache_html(
@@ -251,17 +387,9 @@ impl SqliteMailStore {
         subject: &str,
         body: &str,
     ) -> Result<i64> {
-        let folder_id = if let Some(id) = self.folder_id_by_name(account_id, "Drafts").await? {
-            id
-        } else {
-            let result =
-                sqlx::query("INSERT INTO folders (account_id, name, unread) VALUES (?, ?, 0)")
-                    .bind(account_id)
-                    .bind("Drafts")
-                    .execute(&self.pool)
-                    .await?;
-            result.last_insert_rowid()
-        };
+        let folder_id = self
+            .ensure_folder_for_role(account_id, FolderRole::Drafts)
+            .await?;
 
         let now = Local::now();
         let date = now.format("%Y-%m-%d %H:%M").to_string();
@@ -293,6 +421,99 @@ impl SqliteMailStore {
         Ok(message_id)
     }
 
+    /// Stores the copy of a just-sent message so it shows up in Sent before the
+    /// next sync. A row already synced under `imap_uid` is left untouched.
+    pub async fn save_sent_message(
+        &self,
+        account_id: i64,
+        folder_id: i64,
+        imap_uid: Option<u32>,
+        raw: &[u8],
+    ) -> Result<i64> {
+        let uid = imap_uid.map(|v| v as i64);
+        if let Some(uid) = uid
+            && let Some((id,)) = sqlx::query_as::<_, (i64,)>(
+                "SELECT id FROM messages WHERE folder_id = ? AND imap_uid = ?",
+            )
+            .bind(folder_id)
+            .bind(uid)
+            .fetch_optional(&self.pool)
+            .await?
+        {
+            return Ok(id);
+        }
+
+        let parsed = mailparse::parse_mail(raw)?;
+        let header = |name: &str| parsed.headers.get_first_value(name).unwrap_or_default();
+        let body = first_text_body(&parsed).unwrap_or_default();
+        let date = Local::now().format("%Y-%m-%d %H:%M").to_string();
+
+        let result = sqlx::query(
+            "INSERT INTO messages (account_id, folder_id, imap_uid, date, date_ts, from_addr, to_addr, cc, subject, unread, preview,
+             message_id_header, in_reply_to, references_header, reply_to, size)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(account_id)
+        .bind(folder_id)
+        .bind(uid)
+        .bind(&date)
+        .bind(parse_date_ts(&date))
+        .bind(header("From"))
+        .bind(header("To"))
+        .bind(header("Cc"))
+        .bind(header("Subject"))
+        .bind(0)
+        .bind(draft_preview(&body))
+        .bind(header("Message-ID"))
+        .bind(header("In-Reply-To"))
+        .bind(header("References"))
+        .bind(header("Reply-To"))
+        .bind(raw.len() as i64)
+        .execute(&self.pool)
+        .await?;
+
+        let message_id = result.last_insert_rowid();
+        self.upsert_raw_body(message_id, raw).await?;
+        Ok(message_id)
+    }
+
+    /// Links local draft `message_id` to the copy uploaded to the server. If a
+    /// sync already cached that UID, the local row is dropped as a duplicate.
+    pub async fn mark_draft_uploaded(
+        &self,
+        message_id: i64,
+        imap_uid: Option<u32>,
+        raw: &[u8],
+    ) -> Result<()> {
+        let Some((folder_id,)) =
+            sqlx::query_as::<_, (i64,)>("SELECT folder_id FROM messages WHERE id = ?")
+                .bind(message_id)
+                .fetch_optional(&self.pool)
+                .await?
+        else {
+            return Ok(());
+        };
+        if let Some(uid) = imap_uid {
+            let synced = sqlx::query_as::<_, (i64,)>(
+                "SELECT id FROM messages WHERE folder_id = ? AND imap_uid = ? AND id != ?",
+            )
+            .bind(folder_id)
+            .bind(uid as i64)
+            .bind(message_id)
+            .fetch_optional(&self.pool)
+            .await?;
+            if synced.is_some() {
+                return self.delete_messages(&[message_id]).await;
+            }
+            sqlx::query("UPDATE messages SET imap_uid = ? WHERE id = ?")
+                .bind(uid as i64)
+                .bind(message_id)
+                .execute(&self.pool)
+                .await?;
+        }
+        self.upsert_raw_body(message_id, raw).await
+    }
+
     pub async fn clear_account_data(&self, account_id: i64) -> Result<()> {
         let message_ids: Vec<i64> =
             sqlx::query_as::<_, (i64,)>("SELECT id FROM messages WHERE account_id = ?")
@@ -309,7 +530,14 @@ impl SqliteMailStore {
                 .map(|_| "?")
                 .collect::<Vec<_>>()
                 .join(", ");
-            for table in ["bodies", "cache_text", "cache_html", "cache_tiles"] {
+            for table in [
+                "bodies",
+                "body_missing_parts",
+                "cache_text",
+                "cache_html",
+                "cache_tiles",
+                "message_keywords",
+            ] {
                 let query = format!(
                     "DELETE FROM {} WHERE message_id IN ({})",
                     table, placeholders
@@ -357,20 +585,132 @@ impl SqliteMailStore {
             affected_folders.push(target_folder_id);
         }
 
+        // UIDs belong to their folder, so a moved row gives up its UID until
+        // the server reports the new one; where it came from is kept for
+        // `remap_moved_uids`.
         let update_query = format!(
-            "UPDATE messages SET folder_id = ? WHERE id IN ({})",
+            "UPDATE messages
+             SET folder_id = ?,
+                 moved_from_folder_id = CASE WHEN imap_uid IS NULL
+                     THEN moved_from_folder_id ELSE folder_id END,
+                 moved_from_uid = COALESCE(imap_uid, moved_from_uid),
+                 imap_uid = NULL
+             WHERE id IN ({}) AND folder_id != ?",
             placeholders
         );
         let mut update = sqlx::query(&update_query).bind(target_folder_id);
         for id in message_ids {
             update = update.bind(id);
         }
-        update.execute(&self.pool).await?;
+        update.bind(target_folder_id).execute(&self.pool).await?;
 
         self.update_folder_unread_counts(&affected_folders).await?;
         Ok(())
     }
 
+    /// Gives the messages moved from `source_folder_id` to `folder_id` their
+    /// new UIDs from the server's COPYUID mapping (old UID, new UID). Rows are
+    /// found by the UID they had before the move. A row whose new UID is
+    /// unknown, or was already synced into the folder, is dropped; the next
+    /// sync brings in the server copy.
+    pub async fn remap_moved_uids(
+        &self,
+        source_folder_id: i64,
+        folder_id: i64,
+        uids: &[(u32, Option<u32>)],
+    ) -> Result<()> {
+        let mut duplicates = Vec::new();
+        for (old, new) in uids {
+            let Some((id,)) = sqlx::query_as::<_, (i64,)>(
+                "SELECT id FROM messages
+                 WHERE folder_id = ? AND imap_uid IS NULL
+                   AND moved_from_folder_id = ? AND moved_from_uid = ?",
+            )
+            .bind(folder_id)
+            .bind(source_folder_id)
+            .bind(*old as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            else {
+                continue;
+            };
+            let Some(new) = new else {
+                duplicates.push(id);
+                continue;
+            };
+            let synced = sqlx::query_as::<_, (i64,)>(
+                "SELECT id FROM messages WHERE folder_id = ? AND imap_uid = ?",
+            )
+            .bind(folder_id)
+            .bind(*new as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+            if synced.is_some() {
+                duplicates.push(id);
+                continue;
+            }
+            sqlx::query(
+                "UPDATE messages
+                 SET imap_uid = ?, moved_from_folder_id = NULL, moved_from_uid = NULL
+                 WHERE id = ?",
+            )
+            .bind(*new as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        }
+        self.delete_messages(&duplicates).await
+    }
+
+    /// Puts messages moved out of `source_folder_id` back there with their
+    /// old UIDs, for a move the server refused or the user dropped from the
+    /// queue. Rows are found by the UID they had before the move; one the
+    /// source folder has already synced back in is dropped instead.
+    pub async fn restore_moved_messages(&self, source_folder_id: i64, uids: &[u32]) -> Result<()> {
+        let mut affected_folders = vec![source_folder_id];
+        let mut duplicates = Vec::new();
+        for uid in uids {
+            let Some((id, folder_id)) = sqlx::query_as::<_, (i64, i64)>(
+                "SELECT id, folder_id FROM messages
+                 WHERE imap_uid IS NULL AND moved_from_folder_id = ? AND moved_from_uid = ?",
+            )
+            .bind(source_folder_id)
+            .bind(*uid as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            else {
+                continue;
+            };
+            let synced = sqlx::query_as::<_, (i64,)>(
+                "SELECT id FROM messages WHERE folder_id = ? AND imap_uid = ?",
+            )
+            .bind(source_folder_id)
+            .bind(*uid as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+            if synced.is_some() {
+                duplicates.push(id);
+                continue;
+            }
+            sqlx::query(
+                "UPDATE messages
+                 SET folder_id = ?, imap_uid = ?,
+                     moved_from_folder_id = NULL, moved_from_uid = NULL
+                 WHERE id = ?",
+            )
+            .bind(source_folder_id)
+            .bind(*uid as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+            if !affected_folders.contains(&folder_id) {
+                affected_folders.push(folder_id);
+            }
+        }
+        self.update_folder_unread_counts(&affected_folders).await?;
+        self.delete_messages(&duplicates).await
+    }
+
     pub async fn delete_messages(&self, message_ids: &[i64]) -> Result<()> {
         if message_ids.is_empty() {
             return Ok(());
@@ -392,7 +732,14 @@ impl SqliteMailStore {
             .map(|r| r.0)
             .collect();
 
-        for table in ["bodies", "cache_text", "cache_html", "cache_tiles"] {
+        for table in [
+            "bodies",
+            "body_missing_parts",
+            "cache_text",
+            "cache_html",
+            "cache_tiles",
+            "message_keywords",
+        ] {
             let query = format!(
                 "DELETE FROM {} WHERE message_id IN ({})",
                 table, placeholders
@@ -415,6 +762,92 @@ impl SqliteMailStore {
         Ok(())
     }
 
+    /// Applies server-side changes to cached UIDs `1..=upto_uid`: rows listed in
+    /// `vanished`, or missing from `remaining` when given, are deleted; the rest
+    /// take the unread and flagged state from `flags` and, when listed, the
+    /// keyword set from `keywords`.
+    pub async fn reconcile_folder_messages(
+        &self,
+        folder_id: i64,
+        upto_uid: u32,
+        vanished: &[u32],
+        remaining: Option<&[u32]>,
+        flags: &[(u32, bool, bool)],
+        keywords: &[(u32, Vec<String>)],
+    ) -> Result<()> {
+        let cached = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT id, imap_uid FROM messages
+             WHERE folder_id = ? AND imap_uid IS NOT NULL AND imap_uid <= ?",
+        )
+        .bind(folder_id)
+        .bind(upto_uid as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        let vanished: HashSet<u32> = vanished.iter().copied().collect();
+        let remaining: Option<HashSet<u32>> = remaining.map(|r| r.iter().copied().collect());
+        let gone: Vec<i64> = cached
+            .iter()
+            .filter(|(_, uid)| {
+                let uid = *uid as u32;
+                vanished.contains(&uid)
+                    || remaining
+                        .as_ref()
+                        .is_some_and(|remaining| !remaining.contains(&uid))
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        self.delete_messages(&gone).await?;
+
+        for (uid, unread, flagged) in flags {
+            sqlx::query(
+                "UPDATE messages SET unread = ?, flagged = ?
+                 WHERE folder_id = ? AND imap_uid = ? AND (unread != ? OR flagged != ?)",
+            )
+            .bind(if *unread { 1 } else { 0 })
+            .bind(if *flagged { 1 } else { 0 })
+            .bind(folder_id)
+            .bind(*uid as i64)
+            .bind(if *unread { 1 } else { 0 })
+            .bind(if *flagged { 1 } else { 0 })
+            .execute(&self.pool)
+            .await?;
+        }
+        for (uid, uid_keywords) in keywords {
+            if let Some((id,)) = sqlx::query_as::<_, (i64,)>(
+                "SELECT id FROM messages WHERE folder_id = ? AND imap_uid = ?",
+            )
+            .bind(folder_id)
+            .bind(*uid as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            {
+                self.replace_message_keywords(id, uid_keywords).await?;
+            }
+        }
+        self.update_folder_unread_counts(&[folder_id]).await?;
+        Ok(())
+    }
+
+    /// Drops every cached message, body and render cache in a folder and forgets
+    /// its sync state, e.g. after the server changed UIDVALIDITY.
+    pub async fn invalidate_folder(&self, folder_id: i64) -> Result<()> {
+        let message_ids: Vec<i64> =
+            sqlx::query_as::<_, (i64,)>("SELECT id FROM messages WHERE folder_id = ?")
+                .bind(folder_id)
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|row| row.0)
+                .collect();
+        self.delete_messages(&message_ids).await?;
+        self.update_folder_unread_counts(&[folder_id]).await?;
+        sqlx::query("DELETE FROM folder_sync_state WHERE folder_id = ?")
+            .bind(folder_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     async fn update_folder_unread_counts(&self, folder_ids: &[i64]) -> Result<()> {
         for folder_id in folder_ids {
             let row = sqlx::query_as::<_, (i64,)>(
@@ -447,21 +880,25 @@ impl SqliteMailStore {
         let mut kept_names: Vec<String> = Vec::new();
         let mut output = Vec::new();
         for folder in folders {
+            let role = folder.role.map(FolderRole::as_str);
             let id = if let Some(id) = by_name.get(&folder.name) {
-                sqlx::query("UPDATE folders SET unread = ? WHERE id = ?")
+                sqlx::query("UPDATE folders SET unread = ?, role = ? WHERE id = ?")
                     .bind(folder.unread as i64)
+                    .bind(role)
                     .bind(*id)
                     .execute(&self.pool)
                     .await?;
                 *id
             } else {
-                let result =
-                    sqlx::query("INSERT INTO folders (account_id, name, unread) VALUES (?, ?, ?)")
-                        .bind(account_id)
-                        .bind(&folder.name)
-                        .bind(folder.unread as i64)
-                        .execute(&self.pool)
-                        .await?;
+                let result = sqlx::query(
+                    "INSERT INTO folders (account_id, name, unread, role) VALUES (?, ?, ?, ?)",
+                )
+                .bind(account_id)
+                .bind(&folder.name)
+                .bind(folder.unread as i64)
+                .bind(role)
+                .execute(&self.pool)
+                .await?;
                 result.last_insert_rowid()
             };
             kept_names.push(folder.name.clone());
@@ -470,6 +907,7 @@ impl SqliteMailStore {
                 account_id,
                 name: folder.name.clone(),
                 unread: folder.unread,
+                role: folder.role,
             });
         }
 
@@ -516,20 +954,27 @@ impl SqliteMailStore {
             .collect();
 
         if !existing.is_empty() {
+            // Rows without a UID are local-only (e.g. drafts not yet uploaded)
+            // and are not part of the server listing.
             let mut to_delete = Vec::new();
             for (id, uid) in existing {
-                if let Some(uid) = uid {
-                    if !incoming_uids.contains(&uid) {
-                        to_delete.push(id);
-                    }
-                } else {
+                if let Some(uid) = uid
+                    && !incoming_uids.contains(&uid)
+                {
                     to_delete.push(id);
                 }
             }
 
             if !to_delete.is_empty() {
                 let placeholders = to_delete.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
-                for table in ["bodies", "cache_text", "cache_html", "cache_tiles"] {
+                for table in [
+                    "bodies",
+                    "body_missing_parts",
+                    "cache_text",
+                    "cache_html",
+                    "cache_tiles",
+                    "message_keywords",
+                ] {
                     let query = format!(
                         "DELETE FROM {} WHERE message_id IN ({})",
                         table, placeholders
@@ -570,7 +1015,10 @@ impl SqliteMailStore {
             if let Some(existing_id) = existing_id {
                 // UPDATE existing message
                 sqlx::query(
-                    "UPDATE messages SET date = ?, date_ts = ?, from_addr = ?, subject = ?, unread = ?, preview = ?
+                    "UPDATE messages SET date = ?, date_ts = ?, from_addr = ?, subject = ?, unread = ?, flagged = ?, preview = ?,
+                     gm_msgid = COALESCE(?, gm_msgid), gm_thrid = COALESCE(?, gm_thrid),
+                     to_addr = ?, cc = ?, message_id_header = ?, in_reply_to = ?, references_header = ?,
+                     reply_to = ?, list_id = ?, size = COALESCE(?, size)
                      WHERE id = ?",
                 )
                 .bind(&msg.date)
@@ -578,15 +1026,29 @@ impl SqliteMailStore {
                 .bind(&msg.from)
                 .bind(&msg.subject)
                 .bind(if msg.unread { 1 } else { 0 })
+                .bind(if msg.flagged { 1 } else { 0 })
                 .bind(&msg.preview)
+                .bind(msg.gmail_msgid.map(|v| v as i64))
+                .bind(msg.gmail_thread_id.map(|v| v as i64))
+                .bind(&msg.to)
+                .bind(&msg.cc)
+                .bind(&msg.message_id_header)
+                .bind(&msg.in_reply_to)
+                .bind(&msg.references)
+                .bind(&msg.reply_to)
+                .bind(&msg.list_id)
+                .bind(msg.size.map(|v| v as i64))
                 .bind(existing_id)
                 .execute(&self.pool)
                 .await?;
+                self.replace_message_keywords(existing_id, &msg.keywords)
+                    .await?;
             } else {
                 // INSERT new message
-                sqlx::query(
-                    "INSERT INTO messages (account_id, folder_id, imap_uid, date, date_ts, from_addr, to_addr, cc, subject, unread, preview)
-                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                let result = sqlx::query(
+                    "INSERT INTO messages (account_id, folder_id, imap_uid, date, date_ts, from_addr, to_addr, cc, subject, unread, flagged, preview, gm_msgid, gm_thrid,
+                     message_id_header, in_reply_to, references_header, reply_to, list_id, size)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 )
                 .bind(account_id)
                 .bind(folder_id)
@@ -594,13 +1056,24 @@ impl SqliteMailStore {
                 .bind(&msg.date)
                 .bind(parse_date_ts(&msg.date))
                 .bind(&msg.from)
-                .bind("")
-                .bind("")
+                .bind(&msg.to)
+                .bind(&msg.cc)
                 .bind(&msg.subject)
                 .bind(if msg.unread { 1 } else { 0 })
+                .bind(if msg.flagged { 1 } else { 0 })
                 .bind(&msg.preview)
+                .bind(msg.gmail_msgid.map(|v| v as i64))
+                .bind(msg.gmail_thread_id.map(|v| v as i64))
+                .bind(&msg.message_id_header)
+                .bind(&msg.in_reply_to)
+                .bind(&msg.references)
+                .bind(&msg.reply_to)
+                .bind(&msg.list_id)
+                .bind(msg.size.map(|v| v as i64))
                 .execute(&self.pool)
                 .await?;
+                self.replace_message_keywords(result.last_insert_rowid(), &msg.keywords)
+                    .await?;
             }
         }
         Ok(())
@@ -628,7 +1101,10 @@ impl SqliteMailStore {
             };
             if let Some(id) = exists {
                 sqlx::query(
-                    "UPDATE messages SET date = ?, date_ts = ?, from_addr = ?, subject = ?, unread = ?, preview = ?
+                    "UPDATE messages SET date = ?, date_ts = ?, from_addr = ?, subject = ?, unread = ?, flagged = ?, preview = ?,
+                     gm_msgid = COALESCE(?, gm_msgid), gm_thrid = COALESCE(?, gm_thrid),
+                     to_addr = ?, cc = ?, message_id_header = ?, in_reply_to = ?, references_header = ?,
+                     reply_to = ?, list_id = ?, size = COALESCE(?, size)
                      WHERE id = ?",
                 )
                 .bind(&msg.date)
@@ -636,14 +1112,27 @@ impl SqliteMailStore {
                 .bind(&msg.from)
                 .bind(&msg.subject)
                 .bind(if msg.unread { 1 } else { 0 })
+                .bind(if msg.flagged { 1 } else { 0 })
                 .bind(&msg.preview)
+                .bind(msg.gmail_msgid.map(|v| v as i64))
+                .bind(msg.gmail_thread_id.map(|v| v as i64))
+                .bind(&msg.to)
+                .bind(&msg.cc)
+                .bind(&msg.message_id_header)
+                .bind(&msg.in_reply_to)
+                .bind(&msg.references)
+                .bind(&msg.reply_to)
+                .bind(&msg.list_id)
+                .bind(msg.size.map(|v| v as i64))
                 .bind(id)
                 .execute(&self.pool)
                 .await?;
+                self.replace_message_keywords(id, &msg.keywords).await?;
             } else {
-                sqlx::query(
-                    "INSERT INTO messages (account_id, folder_id, imap_uid, date, date_ts, from_addr, to_addr, cc, subject, unread, preview)
-                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                let result = sqlx::query(
+                    "INSERT INTO messages (account_id, folder_id, imap_uid, date, date_ts, from_addr, to_addr, cc, subject, unread, flagged, preview, gm_msgid, gm_thrid,
+                     message_id_header, in_reply_to, references_header, reply_to, list_id, size)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 )
                 .bind(account_id)
                 .bind(folder_id)
@@ -651,13 +1140,24 @@ impl SqliteMailStore {
                 .bind(&msg.date)
                 .bind(parse_date_ts(&msg.date))
                 .bind(&msg.from)
-                .bind("")
-                .bind("")
+                .bind(&msg.to)
+                .bind(&msg.cc)
                 .bind(&msg.subject)
                 .bind(if msg.unread { 1 } else { 0 })
+                .bind(if msg.flagged { 1 } else { 0 })
                 .bind(&msg.preview)
+                .bind(msg.gmail_msgid.map(|v| v as i64))
+                .bind(msg.gmail_thread_id.map(|v| v as i64))
+                .bind(&msg.message_id_header)
+                .bind(&msg.in_reply_to)
+                .bind(&msg.references)
+                .bind(&msg.reply_to)
+                .bind(&msg.list_id)
+                .bind(msg.size.map(|v| v as i64))
                 .execute(&self.pool)
                 .await?;
+                self.replace_message_keywords(result.last_insert_rowid(), &msg.keywords)
+                    .await?;
             }
         }
         Ok(())
@@ -673,22 +1173,66 @@ impl SqliteMailStore {
         Ok(row.map(|r| r.0))
     }
 
+    pub async fn folder_id_by_role(
+        &self,
+        account_id: i64,
+        role: FolderRole,
+    ) -> Result<Option<i64>> {
+        let row = sqlx::query_as::<_, (i64,)>(
+            "SELECT id FROM folders WHERE account_id = ? AND role = ? ORDER BY id LIMIT 1",
+        )
+        .bind(account_id)
+        .bind(role.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| r.0))
+    }
+
+    /// Returns the folder holding `role`, creating it under the role's default
+    /// name when the account has none (e.g. drafts saved without IMAP).
+    pub async fn ensure_folder_for_role(&self, account_id: i64, role: FolderRole) -> Result<i64> {
+        if let Some(id) = self.folder_id_by_role(account_id, role).await? {
+            return Ok(id);
+        }
+        if let Some(id) = self
+            .folder_id_by_name(account_id, role.default_name())
+            .await?
+        {
+            sqlx::query("UPDATE folders SET role = ? WHERE id = ?")
+                .bind(role.as_str())
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+            return Ok(id);
+        }
+        let result =
+            sqlx::query("INSERT INTO folders (account_id, name, unread, role) VALUES (?, ?, 0, ?)")
+                .bind(account_id)
+                .bind(role.default_name())
+                .bind(role.as_str())
+                .execute(&self.pool)
+                .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_folder(&self, folder_id: i64) -> Result<Option<Folder>> {
+        let row = sqlx::query_as::<_, (i64, i64, String, i64, Option<String>)>(
+            "SELECT id, account_id, name, unread, role FROM folders WHERE id = ?",
+        )
+        .bind(folder_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(folder_from_row))
+    }
+
     pub async fn list_folders(&self, account_id: i64) -> Result<Vec<Folder>> {
-        let folders = sqlx::query_as::<_, (i64, i64, String, i64)>(
-            "SELECT id, account_id, name, unread FROM folders WHERE account_id = ? ORDER BY id",
+        let folders = sqlx::query_as::<_, (i64, i64, String, i64, Option<String>)>(
+            "SELECT id, account_id, name, unread, role FROM folders WHERE account_id = ? ORDER BY id",
         )
         .bind(account_id)
         .fetch_all(&self.pool)
         .await?;
-        Ok(folders
-            .into_iter()
-            .map(|row| Folder {
-                id: row.0,
-                account_id: row.1,
-                name: row.2,
-                unread: row.3 as u32,
-            })
-            .collect())
+        Ok(folders.into_iter().map(folder_from_row).collect())
     }
 
     pub async fn list_messages(
@@ -696,19 +1240,30 @@ impl SqliteMailStore {
         account_id: i64,
         folder_id: Option<i64>,
         unread: Option<bool>,
+        flagged: Option<bool>,
         since_ts: Option<i64>,
         limit: Option<i64>,
     ) -> Result<Vec<MessageSummary>> {
-        let mut query = String::from(
-            "SELECT id, folder_id, imap_uid, date, from_addr, subject, unread, preview
+        let mut query = format!(
+            "SELECT {}
              FROM messages WHERE account_id = ?",
+            SUMMARY_COLUMNS
         );
         if folder_id.is_some() {
             query.push_str(" AND folder_id = ?");
+        } else {
+            // Gmail lists one message in every label folder; keep a single copy.
+            query.push_str(
+                " AND (gm_msgid IS NULL OR id = (SELECT MIN(d.id) FROM messages d
+                   WHERE d.account_id = messages.account_id AND d.gm_msgid = messages.gm_msgid))",
+            );
         }
         if unread.is_some() {
             query.push_str(" AND unread = ?");
         }
+        if flagged.is_some() {
+            query.push_str(" AND flagged = ?");
+        }
         if since_ts.is_some() {
             query.push_str(" AND COALESCE(date_ts, 0) >= ?");
         }
@@ -717,17 +1272,16 @@ impl SqliteMailStore {
             query.push_str(" LIMIT ?");
         }
 
-        let mut q =
-            sqlx::query_as::<_, (i64, i64, Option<i64>, String, String, String, i64, String)>(
-                &query,
-            )
-            .bind(account_id);
+        let mut q = sqlx::query_as::<_, MessageSummaryRow>(&query).bind(account_id);
         if let Some(folder_id) = folder_id {
             q = q.bind(folder_id);
         }
         if let Some(unread) = unread {
             q = q.bind(if unread { 1 } else { 0 });
         }
+        if let Some(flagged) = flagged {
+            q = q.bind(if flagged { 1 } else { 0 });
+        }
         if let Some(since_ts) = since_ts {
             q = q.bind(since_ts);
         }
@@ -736,19 +1290,31 @@ impl SqliteMailStore {
         }
 
         let rows = q.fetch_all(&self.pool).await?;
-        Ok(rows
-            .into_iter()
-            .map(|row| MessageSummary {
-                id: row.0,
-                folder_id: row.1,
-                imap_uid: row.2.map(|v| v as u32),
-                date: row.3,
-                from: row.4,
-                subject: row.5,
-                unread: row.6 != 0,
-                preview: row.7,
-            })
-            .collect())
+        Ok(rows.into_iter().map(message_summary_from_row).collect())
+    }
+
+    /// Summaries of the messages cached in `folder_id` under `uids`.
+    pub async fn list_messages_by_uids(
+        &self,
+        folder_id: i64,
+        uids: &[u32],
+    ) -> Result<Vec<MessageSummary>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = format!(
+            "SELECT {}
+             FROM messages WHERE folder_id = ? AND imap_uid IN ({})
+             ORDER BY COALESCE(date_ts, 0) DESC, id DESC",
+            SUMMARY_COLUMNS,
+            placeholders(uids.len())
+        );
+        let mut q = sqlx::query_as::<_, MessageSummaryRow>(&query).bind(folder_id);
+        for uid in uids {
+            q = q.bind(*uid as i64);
+        }
+        let rows = q.fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(message_summary_from_row).collect())
     }
 
     pub async fn list_sent_from_addresses(
@@ -779,24 +1345,16 @@ impl SqliteMailStore {
     }
 
     pub async fn get_message_summary(&self, message_id: i64) -> Result<Option<MessageSummary>> {
-        let row =
-            sqlx::query_as::<_, (i64, i64, Option<i64>, String, String, String, i64, String)>(
-                "SELECT id, folder_id, imap_uid, date, from_addr, subject, unread, preview
+        let query = format!(
+            "SELECT {}
              FROM messages WHERE id = ?",
-            )
+            SUMMARY_COLUMNS
+        );
+        let row = sqlx::query_as::<_, MessageSummaryRow>(&query)
             .bind(message_id)
             .fetch_optional(&self.pool)
             .await?;
-        Ok(row.map(|row| MessageSummary {
-            id: row.0,
-            folder_id: row.1,
-            imap_uid: row.2.map(|v| v as u32),
-            date: row.3,
-            from: row.4,
-            subject: row.5,
-            unread: row.6 != 0,
-            preview: row.7,
-        }))
+        Ok(row.map(message_summary_from_row))
     }
 
     pub async fn get_message_text(
@@ -865,6 +1423,50 @@ impl SqliteMailStore {
         Ok(())
     }
 
+    pub async fn set_message_flagged(&self, message_id: i64, flagged: bool) -> Result<()> {
+        sqlx::query("UPDATE messages SET flagged = ? WHERE id = ?")
+            .bind(if flagged { 1 } else { 0 })
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Adds and removes keywords on a message; removals win over additions.
+    pub async fn update_message_keywords(
+        &self,
+        message_id: i64,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<()> {
+        for keyword in add {
+            sqlx::query(
+                "INSERT OR IGNORE INTO message_keywords (message_id, keyword) VALUES (?, ?)",
+            )
+            .bind(message_id)
+            .bind(keyword)
+            .execute(&self.pool)
+            .await?;
+        }
+        for keyword in remove {
+            sqlx::query("DELETE FROM message_keywords WHERE message_id = ? AND keyword = ?")
+                .bind(message_id)
+                .bind(keyword)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn replace_message_keywords(&self, message_id: i64, keywords: &[String]) -> Result<()> {
+        sqlx::query("DELETE FROM message_keywords WHERE message_id = ?")
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        self.update_message_keywords(message_id, keywords, &[])
+            .await
+    }
+
     pub async fn first_folder_id(&self, account_id: i64) -> Result<Option<i64>> {
         let row = sqlx::query_as::<_, (i64,)>(
             "SELECT id FROM folders WHERE account_id = ? ORDER BY id LIMIT 1",
@@ -900,9 +1502,11 @@ impl SqliteMailStore {
                 Option<i64>,
                 Option<i64>,
                 Option<i64>,
+                Option<i64>,
             ),
         >(
-            "SELECT folder_id, uidvalidity, uidnext, last_seen_uid, last_sync_ts, oldest_ts
+            "SELECT folder_id, uidvalidity, uidnext, last_seen_uid, last_sync_ts, oldest_ts,
+                    highest_modseq
              FROM folder_sync_state WHERE folder_id = ?",
         )
         .bind(folder_id)
@@ -915,20 +1519,23 @@ impl SqliteMailStore {
             last_seen_uid: r.3,
             last_sync_ts: r.4,
             oldest_ts: r.5,
+            highest_modseq: r.6,
         }))
     }
 
     pub async fn upsert_folder_sync_state(&self, state: &FolderSyncState) -> Result<()> {
         sqlx::query(
             "INSERT INTO folder_sync_state
-             (folder_id, uidvalidity, uidnext, last_seen_uid, last_sync_ts, oldest_ts)
-             VALUES (?, ?, ?, ?, ?, ?)
+             (folder_id, uidvalidity, uidnext, last_seen_uid, last_sync_ts, oldest_ts,
+              highest_modseq)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
              ON CONFLICT(folder_id) DO UPDATE SET
                uidvalidity = excluded.uidvalidity,
                uidnext = excluded.uidnext,
                last_seen_uid = excluded.last_seen_uid,
                last_sync_ts = excluded.last_sync_ts,
-               oldest_ts = excluded.oldest_ts",
+               oldest_ts = excluded.oldest_ts,
+               highest_modseq = excluded.highest_modseq",
         )
         .bind(state.folder_id)
         .bind(state.uidvalidity)
@@ -936,22 +1543,107 @@ impl SqliteMailStore {
         .bind(state.last_seen_uid)
         .bind(state.last_sync_ts)
         .bind(state.oldest_ts)
+        .bind(state.highest_modseq)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    pub async fn seed_demo_if_empty(&self, account_label: &str) -> Result<()> {
-        let trimmed_label = account_label.trim();
-        let lower_label = trimmed_label.to_ascii_lowercase();
-        let is_work_demo = lower_label.contains("work");
-        let expected_address = if is_work_demo {
-            "work@ratmail-demo.local"
-        } else {
-            "personal@ratmail-demo.local"
-        };
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM accounts")
-            .fetch_one(&self.pool)
+    pub async fn enqueue_pending_op(
+        &self,
+        account_id: i64,
+        kind: &str,
+        payload: &str,
+    ) -> Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO pending_ops (account_id, kind, payload, created_ts)
+             VALUES (?, ?, ?, CAST(strftime('%s', 'now') AS INTEGER))",
+        )
+        .bind(account_id)
+        .bind(kind)
+        .bind(payload)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Queued operations of `account_id`, oldest first.
+    pub async fn list_pending_ops(&self, account_id: i64) -> Result<Vec<PendingOp>> {
+        let ops = sqlx::query_as::<_, PendingOp>(
+            "SELECT id, account_id, kind, payload, created_ts, attempts, last_error,
+                    last_attempt_ts, failed, draft_id
+             FROM pending_ops WHERE account_id = ? ORDER BY id",
+        )
+        .bind(account_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ops)
+    }
+
+    pub async fn get_pending_op(&self, op_id: i64) -> Result<Option<PendingOp>> {
+        let op = sqlx::query_as::<_, PendingOp>(
+            "SELECT id, account_id, kind, payload, created_ts, attempts, last_error,
+                    last_attempt_ts, failed, draft_id
+             FROM pending_ops WHERE id = ?",
+        )
+        .bind(op_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(op)
+    }
+
+    /// Counts a failed attempt. With `failed` set the operation is parked:
+    /// replay skips it until it is retried by hand.
+    pub async fn record_pending_op_failure(
+        &self,
+        op_id: i64,
+        error: &str,
+        failed: bool,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE pending_ops
+             SET attempts = attempts + 1, last_error = ?, failed = ?,
+                 last_attempt_ts = CAST(strftime('%s', 'now') AS INTEGER)
+             WHERE id = ?",
+        )
+        .bind(error)
+        .bind(failed)
+        .bind(op_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_pending_op_draft(&self, op_id: i64, draft_id: i64) -> Result<()> {
+        sqlx::query("UPDATE pending_ops SET draft_id = ? WHERE id = ?")
+            .bind(draft_id)
+            .bind(op_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Removes an operation once it went through, or when it is dropped.
+    /// Returns whether it was still queued.
+    pub async fn delete_pending_op(&self, op_id: i64) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM pending_ops WHERE id = ?")
+            .bind(op_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn seed_demo_if_empty(&self, account_label: &str) -> Result<()> {
+        let trimmed_label = account_label.trim();
+        let lower_label = trimmed_label.to_ascii_lowercase();
+        let is_work_demo = lower_label.contains("work");
+        let expected_address = if is_work_demo {
+            "work@ratmail-demo.local"
+        } else {
+            "personal@ratmail-demo.local"
+        };
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM accounts")
+            .fetch_one(&self.pool)
             .await?;
         let should_reset_demo = if count.0 == 0 {
             true
@@ -991,6 +1683,12 @@ impl SqliteMailStore {
             sqlx::query("DELETE FROM bodies")
                 .execute(&self.pool)
                 .await?;
+            sqlx::query("DELETE FROM body_missing_parts")
+                .execute(&self.pool)
+                .await?;
+            sqlx::query("DELETE FROM message_keywords")
+                .execute(&self.pool)
+                .await?;
             sqlx::query("DELETE FROM messages")
                 .execute(&self.pool)
                 .await?;
@@ -1020,22 +1718,24 @@ impl SqliteMailStore {
             .await?;
 
         let folders = vec![
-            (1, "INBOX", 4),
-            (2, "Sent", 0),
-            (3, "Drafts", 1),
-            (4, "Archive", 0),
-            (5, "Promotions", 2),
-            (6, "Orders", 1),
+            (1, "INBOX", 4, None),
+            (2, "Sent", 0, Some(FolderRole::Sent)),
+            (3, "Drafts", 1, Some(FolderRole::Drafts)),
+            (4, "Archive", 0, Some(FolderRole::Archive)),
+            (5, "Promotions", 2, None),
+            (6, "Orders", 1, None),
         ];
 
-        for (id, name, unread) in folders {
+        for (id, name, unread, role) in folders {
             sqlx::query(
-                "INSERT INTO folders (id, account_id, name, unread) VALUES (?, 1, ?, ?)
-                 ON CONFLICT(id) DO UPDATE SET name = excluded.name, unread = excluded.unread",
+                "INSERT INTO folders (id, account_id, name, unread, role) VALUES (?, 1, ?, ?, ?)
+                 ON CONFLICT(id) DO UPDATE SET name = excluded.name, unread = excluded.unread,
+                 role = excluded.role",
             )
             .bind(id)
             .bind(name)
             .bind(unread)
+            .bind(role.map(FolderRole::as_str))
             .execute(&self.pool)
             .await?;
         }
@@ -1750,6 +2450,83 @@ Packing list:\r\n\
     }
 }
 
+fn folder_from_row(row: (i64, i64, String, i64, Option<String>)) -> Folder {
+    Folder {
+        id: row.0,
+        account_id: row.1,
+        name: row.2,
+        unread: row.3 as u32,
+        role: row.4.as_deref().and_then(FolderRole::parse),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct MessageSummaryRow {
+    id: i64,
+    folder_id: i64,
+    imap_uid: Option<i64>,
+    date: String,
+    from_addr: String,
+    subject: String,
+    unread: i64,
+    preview: String,
+    flagged: i64,
+    gm_msgid: Option<i64>,
+    gm_thrid: Option<i64>,
+    to_addr: String,
+    cc: String,
+    message_id_header: String,
+    in_reply_to: String,
+    references_header: String,
+    reply_to: String,
+    list_id: String,
+    size: Option<i64>,
+    keywords: Option<String>,
+}
+
+/// Columns of `MessageSummaryRow`. Keywords are newline-separated since Gmail
+/// labels may contain spaces.
+const SUMMARY_COLUMNS: &str =
+    "id, folder_id, imap_uid, date, from_addr, subject, unread, preview, flagged,
+     gm_msgid, gm_thrid, to_addr, cc, message_id_header, in_reply_to, references_header, reply_to,
+     list_id, size,
+     (SELECT group_concat(keyword, char(10)) FROM message_keywords k
+      WHERE k.message_id = messages.id) AS keywords";
+
+fn message_summary_from_row(row: MessageSummaryRow) -> MessageSummary {
+    let mut keywords: Vec<String> = row
+        .keywords
+        .as_deref()
+        .unwrap_or_default()
+        .split('\n')
+        .filter(|keyword| !keyword.is_empty())
+        .map(str::to_string)
+        .collect();
+    keywords.sort_unstable();
+    MessageSummary {
+        id: row.id,
+        folder_id: row.folder_id,
+        imap_uid: row.imap_uid.map(|v| v as u32),
+        date: row.date,
+        from: row.from_addr,
+        subject: row.subject,
+        unread: row.unread != 0,
+        flagged: row.flagged != 0,
+        keywords,
+        gmail_msgid: row.gm_msgid.map(|v| v as u64),
+        gmail_thread_id: row.gm_thrid.map(|v| v as u64),
+        preview: row.preview,
+        to: row.to_addr,
+        cc: row.cc,
+        message_id_header: row.message_id_header,
+        in_reply_to: row.in_reply_to,
+        references: row.references_header,
+        reply_to: row.reply_to,
+        list_id: row.list_id,
+        size: row.size.map(|v| v as u32),
+    }
+}
+
 fn placeholders(count: usize) -> String {
     std::iter::repeat("?")
         .take(count)
@@ -1767,23 +2544,24 @@ impl MailStore for SqliteMailStore {
         .fetch_one(&self.pool)
         .await?;
 
-        let folders = sqlx::query_as::<_, (i64, i64, String, i64)>(
-            "SELECT id, account_id, name, unread FROM folders WHERE account_id = ? ORDER BY id",
+        let folders = sqlx::query_as::<_, (i64, i64, String, i64, Option<String>)>(
+            "SELECT id, account_id, name, unread, role FROM folders WHERE account_id = ? ORDER BY id",
         )
         .bind(account_id)
         .fetch_all(&self.pool)
         .await?;
 
-        let messages =
-            sqlx::query_as::<_, (i64, i64, Option<i64>, String, String, String, i64, String)>(
-                "SELECT id, folder_id, imap_uid, date, from_addr, subject, unread, preview
+        let query = format!(
+            "SELECT {}
              FROM messages WHERE account_id = ? ORDER BY COALESCE(date_ts, 0) DESC, id DESC",
-            )
+            SUMMARY_COLUMNS
+        );
+        let messages = sqlx::query_as::<_, MessageSummaryRow>(&query)
             .bind(account_id)
             .fetch_all(&self.pool)
             .await?;
 
-        let message_ids: Vec<i64> = messages.iter().map(|row| row.0).collect();
+        let message_ids: Vec<i64> = messages.iter().map(|row| row.id).collect();
         let mut message_details = HashMap::new();
 
         for message_id in message_ids {
@@ -1822,28 +2600,8 @@ impl MailStore for SqliteMailStore {
                 name: account.1,
                 address: account.2,
             },
-            folders: folders
-                .into_iter()
-                .map(|row| Folder {
-                    id: row.0,
-                    account_id: row.1,
-                    name: row.2,
-                    unread: row.3 as u32,
-                })
-                .collect(),
-            messages: messages
-                .into_iter()
-                .map(|row| MessageSummary {
-                    id: row.0,
-                    folder_id: row.1,
-                    imap_uid: row.2.map(|v| v as u32),
-                    date: row.3,
-                    from: row.4,
-                    subject: row.5,
-                    unread: row.6 != 0,
-                    preview: row.7,
-                })
-                .collect(),
+            folders: folders.into_iter().map(folder_from_row).collect(),
+            messages: messages.into_iter().map(message_summary_from_row).collect(),
             message_details,
         })
     }
@@ -1858,6 +2616,25 @@ impl MailStore for SqliteMailStore {
     }
 
     async fn upsert_raw_body(&self, message_id: i64, raw: &[u8]) -> Result<()> {
+        self.upsert_partial_body(message_id, raw, &[]).await
+    }
+
+    async fn get_missing_parts(&self, message_id: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query_as::<_, (String,)>(
+            "SELECT section FROM body_missing_parts WHERE message_id = ? ORDER BY section",
+        )
+        .bind(message_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|r| r.0).collect())
+    }
+
+    async fn upsert_partial_body(
+        &self,
+        message_id: i64,
+        raw: &[u8],
+        missing_parts: &[String],
+    ) -> Result<()> {
         sqlx::query(
             "INSERT INTO bodies (message_id, raw_bytes) VALUES (?, ?)
              ON CONFLICT(message_id) DO UPDATE SET raw_bytes = excluded.raw_bytes",
@@ -1866,6 +2643,17 @@ impl MailStore for SqliteMailStore {
         .bind(raw)
         .execute(&self.pool)
         .await?;
+        sqlx::query("DELETE FROM body_missing_parts WHERE message_id = ?")
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        for section in missing_parts {
+            sqlx::query("INSERT INTO body_missing_parts (message_id, section) VALUES (?, ?)")
+                .bind(message_id)
+                .bind(section)
+                .execute(&self.pool)
+                .await?;
+        }
         Ok(())
     }
 
@@ -1988,7 +2776,7 @@ mod tests {
     use std::path::PathBuf;
     use std::time::{SystemTime, UNIX_EPOCH};
 
-    use super::{MailStore, SqliteMailStore};
+    use super::{Folder, FolderRole, FolderSyncState, MailStore, MessageSummary, SqliteMailStore};
 
     fn temp_db_path() -> PathBuf {
         let ts = SystemTime::now()
@@ -2002,6 +2790,86 @@ mod tests {
         ))
     }
 
+    /// A migrated store in a temporary file, removed again on drop.
+    struct TestStore {
+        store: SqliteMailStore,
+        path: PathBuf,
+        /// The folders passed to `fresh_store`, with their ids.
+        folders: Vec<Folder>,
+    }
+
+    impl std::ops::Deref for TestStore {
+        type Target = SqliteMailStore;
+
+        fn deref(&self) -> &SqliteMailStore {
+            &self.store
+        }
+    }
+
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    /// Store with account 1 and a plain folder for each of `folders`.
+    async fn fresh_store(folders: &[&str]) -> anyhow::Result<TestStore> {
+        let path = temp_db_path();
+        let _ = std::fs::remove_file(&path);
+        let store = SqliteMailStore::connect(
+            path.to_str()
+                .ok_or_else(|| anyhow::anyhow!("invalid temp db path"))?,
+        )
+        .await?;
+        let mut store = TestStore {
+            store,
+            path,
+            folders: Vec::new(),
+        };
+        store.init().await?;
+        store
+            .upsert_account(1, "Personal", "owner@example.com")
+            .await?;
+        let folders: Vec<Folder> = folders
+            .iter()
+            .map(|name| Folder {
+                id: 0,
+                account_id: 1,
+                name: name.to_string(),
+                unread: 0,
+                role: None,
+            })
+            .collect();
+        store.folders = store.upsert_folders(1, &folders).await?;
+        Ok(store)
+    }
+
+    /// A synced read message in `folder_id`; tests override what they check.
+    fn summary(folder_id: i64, uid: u32) -> MessageSummary {
+        MessageSummary {
+            id: 0,
+            folder_id,
+            imap_uid: Some(uid),
+            date: "Mon, 01 Jan 2024 10:00:00 +0000".to_string(),
+            from: "sender@example.com".to_string(),
+            subject: format!("Message {}", uid),
+            unread: false,
+            flagged: false,
+            keywords: Vec::new(),
+            gmail_msgid: None,
+            gmail_thread_id: None,
+            preview: String::new(),
+            to: String::new(),
+            cc: String::new(),
+            message_id_header: String::new(),
+            in_reply_to: String::new(),
+            references: String::new(),
+            reply_to: String::new(),
+            list_id: String::new(),
+            size: None,
+        }
+    }
+
     #[tokio::test]
     async fn init_applies_full_migration_chain_on_fresh_db() -> anyhow::Result<()> {
         let db_path = temp_db_path();
@@ -2035,6 +2903,12 @@ mod tests {
             "subject",
             "unread",
             "preview",
+            "message_id_header",
+            "in_reply_to",
+            "references_header",
+            "reply_to",
+            "list_id",
+            "size",
         ] {
             assert!(columns.contains(required), "missing column {}", required);
         }
@@ -2086,7 +2960,12 @@ mod tests {
         let snapshot = store.load_snapshot(1, drafts_folder_id).await?;
 
         assert_eq!(snapshot.account.id, 1);
-        assert!(snapshot.folders.iter().any(|f| f.name == "Drafts"));
+        assert!(
+            snapshot
+                .folders
+                .iter()
+                .any(|f| f.name == "Drafts" && f.role == Some(FolderRole::Drafts))
+        );
         assert!(snapshot.messages.iter().any(|m| m.id == msg_id));
 
         let detail = snapshot
@@ -2101,4 +2980,543 @@ mod tests {
         let _ = std::fs::remove_file(&db_path);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn drafts_are_saved_into_the_folder_with_the_drafts_role() -> anyhow::Result<()> {
+        let store = fresh_store(&[]).await?;
+        store
+            .upsert_folders(
+                1,
+                &[
+                    Folder {
+                        id: 0,
+                        account_id: 1,
+                        name: "INBOX".to_string(),
+                        unread: 0,
+                        role: None,
+                    },
+                    Folder {
+                        id: 0,
+                        account_id: 1,
+                        name: "[Gmail]/Entwürfe".to_string(),
+                        unread: 0,
+                        role: Some(FolderRole::Drafts),
+                    },
+                ],
+            )
+            .await?;
+
+        let msg_id = store
+            .save_draft(1, "owner@example.com", "", "", "", "Entwurf", "Hallo")
+            .await?;
+
+        let drafts_id = store
+            .folder_id_by_role(1, FolderRole::Drafts)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing drafts role"))?;
+        let folders = store.list_folders(1).await?;
+        assert_eq!(folders.len(), 2);
+        assert!(
+            folders
+                .iter()
+                .any(|f| f.id == drafts_id && f.name == "[Gmail]/Entwürfe")
+        );
+        let drafts = store
+            .list_messages(1, Some(drafts_id), None, None, None, None)
+            .await?;
+        assert!(drafts.iter().any(|m| m.id == msg_id));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn uploaded_drafts_take_their_uid_and_local_drafts_survive_sync() -> anyhow::Result<()> {
+        let store = fresh_store(&[]).await?;
+
+        let uploaded = store
+            .save_draft(1, "owner@example.com", "", "", "", "Uploaded", "one")
+            .await?;
+        let local = store
+            .save_draft(1, "owner@example.com", "", "", "", "Local", "two")
+            .await?;
+        let drafts_id = store.ensure_folder_for_role(1, FolderRole::Drafts).await?;
+        store
+            .mark_draft_uploaded(uploaded, Some(12), b"Subject: Uploaded\r\n\r\none\r\n")
+            .await?;
+
+        let server_copy = MessageSummary {
+            from: "owner@example.com".to_string(),
+            subject: "Uploaded".to_string(),
+            preview: "one".to_string(),
+            ..summary(drafts_id, 12)
+        };
+        store
+            .replace_folder_messages(1, drafts_id, &[server_copy])
+            .await?;
+
+        let drafts = store
+            .list_messages(1, Some(drafts_id), None, None, None, None)
+            .await?;
+        assert_eq!(drafts.len(), 2);
+        assert!(
+            drafts
+                .iter()
+                .any(|m| m.id == uploaded && m.imap_uid == Some(12))
+        );
+        assert!(drafts.iter().any(|m| m.id == local && m.imap_uid.is_none()));
+        assert_eq!(
+            store.get_raw_body(uploaded).await?.as_deref(),
+            Some(&b"Subject: Uploaded\r\n\r\none\r\n"[..])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn save_sent_message_stores_summary_and_raw_body_once() -> anyhow::Result<()> {
+        let store = fresh_store(&[]).await?;
+        let sent_id = store.ensure_folder_for_role(1, FolderRole::Sent).await?;
+
+        let raw = concat!(
+            "From: Owner <owner@example.com>\r\n",
+            "To: friend@example.com\r\n",
+            "Subject: Lunch\r\n",
+            "Content-Type: multipart/alternative; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See you at noon\r\n",
+            "--b\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<p>See you at noon</p>\r\n",
+            "--b--\r\n",
+        )
+        .as_bytes();
+        let first = store.save_sent_message(1, sent_id, Some(7), raw).await?;
+        let again = store.save_sent_message(1, sent_id, Some(7), raw).await?;
+        assert_eq!(first, again);
+
+        let messages = store
+            .list_messages(1, Some(sent_id), None, None, None, None)
+            .await?;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].imap_uid, Some(7));
+        assert_eq!(messages[0].subject, "Lunch");
+        assert_eq!(messages[0].preview, "See you at noon");
+        assert!(!messages[0].unread);
+        assert_eq!(store.get_raw_body(first).await?.as_deref(), Some(raw));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn partial_bodies_track_missing_parts() -> anyhow::Result<()> {
+        let store = fresh_store(&[]).await?;
+        let sent_id = store.ensure_folder_for_role(1, FolderRole::Sent).await?;
+        let id = store
+            .save_sent_message(1, sent_id, Some(3), b"Subject: Big\r\n\r\nbody\r\n")
+            .await?;
+
+        let missing = vec!["2".to_string(), "3.1".to_string()];
+        store.upsert_partial_body(id, b"partial", &missing).await?;
+        assert_eq!(store.get_missing_parts(id).await?, missing);
+
+        store
+            .upsert_partial_body(id, b"less partial", &missing[1..])
+            .await?;
+        assert_eq!(store.get_missing_parts(id).await?, vec!["3.1".to_string()]);
+
+        store.upsert_raw_body(id, b"complete").await?;
+        assert!(store.get_missing_parts(id).await?.is_empty());
+        assert_eq!(
+            store.get_raw_body(id).await?.as_deref(),
+            Some(&b"complete"[..])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalidate_folder_drops_messages_bodies_and_sync_state() -> anyhow::Result<()> {
+        let store = fresh_store(&["INBOX"]).await?;
+        let inbox_id = store
+            .folder_id_by_name(1, "INBOX")
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing INBOX folder"))?;
+        store
+            .upsert_folder_messages_append(
+                1,
+                inbox_id,
+                &[MessageSummary {
+                    subject: "Stale".to_string(),
+                    unread: true,
+                    ..summary(inbox_id, 7)
+                }],
+            )
+            .await?;
+        let message_id = store
+            .list_messages(1, Some(inbox_id), None, None, None, None)
+            .await?[0]
+            .id;
+        store
+            .upsert_raw_body(message_id, b"Subject: Stale\r\n\r\nbody")
+            .await?;
+        store
+            .upsert_folder_sync_state(&FolderSyncState {
+                folder_id: inbox_id,
+                uidvalidity: Some(1),
+                uidnext: Some(8),
+                last_seen_uid: Some(7),
+                last_sync_ts: Some(0),
+                oldest_ts: Some(0),
+                highest_modseq: None,
+            })
+            .await?;
+
+        store.invalidate_folder(inbox_id).await?;
+
+        assert!(
+            store
+                .list_messages(1, Some(inbox_id), None, None, None, None)
+                .await?
+                .is_empty()
+        );
+        assert!(store.get_raw_body(message_id).await?.is_none());
+        assert!(store.get_folder_sync_state(inbox_id).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reconcile_folder_messages_applies_expunges_and_flags() -> anyhow::Result<()> {
+        let store = fresh_store(&["INBOX"]).await?;
+        let inbox_id = store
+            .folder_id_by_name(1, "INBOX")
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing INBOX folder"))?;
+        let messages: Vec<MessageSummary> = (1..=4)
+            .map(|uid| MessageSummary {
+                unread: true,
+                ..summary(inbox_id, uid)
+            })
+            .collect();
+        store
+            .upsert_folder_messages_append(1, inbox_id, &messages)
+            .await?;
+
+        // UID 4 is newer than the reconciled range and must be left alone.
+        store
+            .reconcile_folder_messages(
+                inbox_id,
+                3,
+                &[2],
+                Some(&[1, 2]),
+                &[(1, false, true)],
+                &[(1, vec!["work".to_string()])],
+            )
+            .await?;
+
+        let remaining = store
+            .list_messages(1, Some(inbox_id), None, None, None, None)
+            .await?;
+        let mut uids: Vec<u32> = remaining.iter().filter_map(|m| m.imap_uid).collect();
+        uids.sort_unstable();
+        assert_eq!(uids, vec![1, 4]);
+        assert!(
+            remaining
+                .iter()
+                .any(|m| m.imap_uid == Some(1) && !m.unread && m.flagged)
+        );
+        let folders = store.list_folders(1).await?;
+        let inbox = folders
+            .iter()
+            .find(|f| f.id == inbox_id)
+            .ok_or_else(|| anyhow::anyhow!("missing INBOX folder"))?;
+        assert_eq!(inbox.unread, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keywords_follow_sync_and_local_edits() -> anyhow::Result<()> {
+        let store = fresh_store(&["INBOX"]).await?;
+        let inbox_id = store
+            .folder_id_by_name(1, "INBOX")
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing INBOX folder"))?;
+        let message = MessageSummary {
+            keywords: vec!["work".to_string(), "$label1".to_string()],
+            ..summary(inbox_id, 1)
+        };
+        store
+            .upsert_folder_messages_append(1, inbox_id, &[message])
+            .await?;
+        let listed = store
+            .list_messages(1, Some(inbox_id), None, None, None, None)
+            .await?;
+        let message_id = listed[0].id;
+        assert_eq!(listed[0].keywords, vec!["$label1", "work"]);
+
+        store
+            .reconcile_folder_messages(inbox_id, 1, &[], None, &[], &[(1, vec!["home".into()])])
+            .await?;
+        store
+            .update_message_keywords(message_id, &["urgent".to_string()], &["home".to_string()])
+            .await?;
+        let summary = store
+            .get_message_summary(message_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing message"))?;
+        assert_eq!(summary.keywords, vec!["urgent"]);
+
+        store.delete_messages(&[message_id]).await?;
+        let (left,) = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM message_keywords")
+            .fetch_one(&store.pool)
+            .await?;
+        assert_eq!(left, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn synced_headers_are_stored_and_refreshed() -> anyhow::Result<()> {
+        let store = fresh_store(&[]).await?;
+        let archive_id = store.ensure_folder_for_role(1, FolderRole::Archive).await?;
+        let mut message = MessageSummary {
+            from: "alice@example.com".to_string(),
+            subject: "Re: Budget".to_string(),
+            unread: true,
+            preview: "Looks good to me".to_string(),
+            to: "owner@example.com".to_string(),
+            cc: "bob@example.com".to_string(),
+            message_id_header: "<b2@example.com>".to_string(),
+            in_reply_to: "<b1@example.com>".to_string(),
+            references: "<b0@example.com> <b1@example.com>".to_string(),
+            reply_to: "team@example.com".to_string(),
+            list_id: "<budget.example.com>".to_string(),
+            size: Some(5120),
+            ..summary(archive_id, 4)
+        };
+        store
+            .upsert_folder_messages_append(1, archive_id, std::slice::from_ref(&message))
+            .await?;
+        let listed = store
+            .list_messages(1, Some(archive_id), None, None, None, None)
+            .await?;
+        assert_eq!(listed[0].cc, "bob@example.com");
+        assert_eq!(listed[0].references, "<b0@example.com> <b1@example.com>");
+        assert_eq!(listed[0].list_id, "<budget.example.com>");
+        assert_eq!(listed[0].size, Some(5120));
+
+        // A later sync without RFC822.SIZE keeps the known size.
+        message.unread = false;
+        message.size = None;
+        store
+            .upsert_folder_messages_append(1, archive_id, &[message])
+            .await?;
+        let summary = store
+            .get_message_summary(listed[0].id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing message"))?;
+        assert!(!summary.unread);
+        assert_eq!(summary.size, Some(5120));
+        assert_eq!(summary.in_reply_to, "<b1@example.com>");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gmail_label_copies_collapse_across_folders() -> anyhow::Result<()> {
+        let store = fresh_store(&["INBOX", "Work", "[Gmail]/All Mail"]).await?;
+        let folders = store.folders.clone();
+        for (folder, uid) in folders.iter().zip([3u32, 9, 40]) {
+            let copy = MessageSummary {
+                subject: "Labelled".to_string(),
+                unread: true,
+                keywords: vec!["Work".to_string(), "Muy Importante".to_string()],
+                gmail_msgid: Some(1_278_455_344_230_334_865),
+                gmail_thread_id: Some(1_278_455_344_230_334_800),
+                ..summary(folder.id, uid)
+            };
+            store
+                .upsert_folder_messages_append(1, folder.id, &[copy])
+                .await?;
+        }
+
+        let all = store.list_messages(1, None, None, None, None, None).await?;
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].gmail_thread_id, Some(1_278_455_344_230_334_800));
+        assert_eq!(all[0].keywords, vec!["Muy Importante", "Work"]);
+        let work = store
+            .list_messages(1, Some(folders[1].id), None, None, None, None)
+            .await?;
+        assert_eq!(work.len(), 1);
+        assert_eq!(work[0].imap_uid, Some(9));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn moved_messages_take_their_copyuid_uids() -> anyhow::Result<()> {
+        let store = fresh_store(&["INBOX", "Archive"]).await?;
+        let inbox_id = store
+            .folder_id_by_name(1, "INBOX")
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing INBOX folder"))?;
+        let archive_id = store
+            .folder_id_by_name(1, "Archive")
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing Archive folder"))?;
+        store
+            .upsert_folder_messages_append(
+                1,
+                inbox_id,
+                &[
+                    summary(inbox_id, 1),
+                    summary(inbox_id, 2),
+                    summary(inbox_id, 3),
+                ],
+            )
+            .await?;
+        // UID 12 reached Archive through a sync before the COPYUID mapping;
+        // Archive's own UID 1 is a different message than INBOX UID 1.
+        store
+            .upsert_folder_messages_append(
+                1,
+                archive_id,
+                &[summary(archive_id, 1), summary(archive_id, 12)],
+            )
+            .await?;
+        let moved: Vec<i64> = store
+            .list_messages(1, Some(inbox_id), None, None, None, None)
+            .await?
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        store.move_messages(&moved, archive_id).await?;
+        let archived = store
+            .list_messages(1, Some(archive_id), None, None, None, None)
+            .await?;
+        assert_eq!(archived.len(), 5);
+        assert_eq!(archived.iter().filter(|m| m.imap_uid.is_none()).count(), 3);
+
+        // UID 3 got no COPYUID: its row goes and the next sync brings it back.
+        store
+            .remap_moved_uids(
+                inbox_id,
+                archive_id,
+                &[(1, Some(2)), (2, Some(12)), (3, None)],
+            )
+            .await?;
+
+        let archived = store
+            .list_messages(1, Some(archive_id), None, None, None, None)
+            .await?;
+        let mut uids: Vec<u32> = archived.iter().filter_map(|m| m.imap_uid).collect();
+        uids.sort_unstable();
+        assert_eq!(uids, vec![1, 2, 12]);
+        assert_eq!(archived.len(), 3);
+        assert!(
+            archived
+                .iter()
+                .any(|m| m.imap_uid == Some(2) && m.subject == "Message 1")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refused_moves_put_messages_back() -> anyhow::Result<()> {
+        let store = fresh_store(&["INBOX", "Archive"]).await?;
+        let (inbox_id, archive_id) = (store.folders[0].id, store.folders[1].id);
+        store
+            .upsert_folder_messages_append(
+                1,
+                inbox_id,
+                &[
+                    MessageSummary {
+                        unread: true,
+                        ..summary(inbox_id, 1)
+                    },
+                    summary(inbox_id, 2),
+                ],
+            )
+            .await?;
+        let moved: Vec<i64> = store
+            .list_messages(1, Some(inbox_id), None, None, None, None)
+            .await?
+            .iter()
+            .map(|m| m.id)
+            .collect();
+        store.move_messages(&moved, archive_id).await?;
+        // The source folder synced UID 2 back in before the refusal arrived.
+        store
+            .upsert_folder_messages_append(1, inbox_id, &[summary(inbox_id, 2)])
+            .await?;
+
+        store.restore_moved_messages(inbox_id, &[1, 2]).await?;
+
+        assert!(
+            store
+                .list_messages(1, Some(archive_id), None, None, None, None)
+                .await?
+                .is_empty()
+        );
+        let inbox = store
+            .list_messages(1, Some(inbox_id), None, None, None, None)
+            .await?;
+        let mut uids: Vec<u32> = inbox.iter().filter_map(|m| m.imap_uid).collect();
+        uids.sort_unstable();
+        assert_eq!(uids, vec![1, 2]);
+        assert!(inbox.iter().any(|m| m.imap_uid == Some(1) && m.unread));
+        let folders = store.list_folders(1).await?;
+        assert!(folders.iter().any(|f| f.id == inbox_id && f.unread == 1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pending_ops_keep_their_order_and_failures() -> anyhow::Result<()> {
+        let store = fresh_store(&[]).await?;
+
+        let first = store.enqueue_pending_op(1, "move", "{\"m\":1}").await?;
+        let second = store.enqueue_pending_op(1, "set_flag", "{\"f\":2}").await?;
+        store
+            .record_pending_op_failure(first, "offline", false)
+            .await?;
+        store
+            .record_pending_op_failure(first, "still offline", false)
+            .await?;
+        store
+            .record_pending_op_failure(second, "NO [NONEXISTENT]", true)
+            .await?;
+        store.set_pending_op_draft(second, 7).await?;
+
+        let ops = store.list_pending_ops(1).await?;
+        assert_eq!(
+            ops.iter().map(|op| op.id).collect::<Vec<_>>(),
+            vec![first, second]
+        );
+        assert_eq!(ops[0].kind, "move");
+        assert_eq!(ops[0].attempts, 2);
+        assert_eq!(ops[0].last_error.as_deref(), Some("still offline"));
+        assert!(ops[0].last_attempt_ts.is_some());
+        assert!(!ops[0].failed);
+        assert!(ops[1].failed);
+        assert!(ops[1].created_ts > 0);
+        let parked = store.get_pending_op(second).await?.expect("op");
+        assert_eq!(parked.draft_id, Some(7));
+        assert_eq!(parked.last_error.as_deref(), Some("NO [NONEXISTENT]"));
+
+        assert!(store.delete_pending_op(first).await?);
+        assert!(!store.delete_pending_op(first).await?);
+        // Ids are never reused, so a late answer cannot hit a newer op.
+        let third = store.enqueue_pending_op(1, "delete", "{}").await?;
+        assert!(third > second);
+        assert_eq!(store.list_pending_ops(1).await?.len(), 2);
+
+        Ok(())
+    }
 }
@@ -3,14 +3,14 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use ratatui_image::picker::Picker;
-use ratmail_core::{SqliteMailStore, StoreSnapshot, log_debug};
+use ratmail_core::{Folder, SqliteMailStore, StoreSnapshot, log_debug};
 use ratmail_mail::{MailCommand, MailEngine, MailEvent};
 
 use super::{
-    App, ComposeFocus, ComposeVimMode, Focus, IMAP_SPINNER_FRAMES, Mode, PickerFocus,
+    App, ComposeFocus, ComposeVimMode, Focus, IMAP_SPINNER_FRAMES, Mode, PendingOps, PickerFocus,
     PickerPreviewKind, RAT_SPINNER_FRAMES, RenderEvent, RenderRequest, SearchSpec, SendConfig,
-    StoreUpdate, UiTheme, ViewMode, canonical_folder_name, compose_buffer_from_body, extract_email,
-    parse_from_addrs,
+    StoreUpdate, SyncPoll, UiTheme, ViewMode, compose_buffer_from_body, extract_email,
+    folder_display_name, parse_from_addrs,
 };
 
 const MAX_MAIL_EVENTS_PER_FRAME: usize = 32;
@@ -36,7 +36,11 @@ impl App {
         render_tile_height_px_side: i64,
         render_tile_height_px_focus: i64,
         imap_enabled: bool,
+        permanent_delete: bool,
+        gmail_labels: bool,
         initial_sync_days: i64,
+        folder_sync_days: HashMap<String, i64>,
+        sync_poll: SyncPoll,
         render_scale: f64,
         folder_pane_width: u16,
         ui_theme_name: String,
@@ -92,6 +96,7 @@ impl App {
             render_pending_tile_height_px: 0,
             render_pending_width_px: 0,
             pending_body_fetch: HashSet::new(),
+            pending_part_fetch: HashSet::new(),
             image_picker,
             image_protocol: None,
             protocol_cache: HashMap::new(),
@@ -126,6 +131,7 @@ impl App {
             search_query: String::new(),
             search_cursor: 0,
             search_spec: SearchSpec::default(),
+            server_search: None,
             search_attachment_queue: VecDeque::new(),
             attachment_checked: HashSet::new(),
             attachment_cache: HashMap::new(),
@@ -133,6 +139,8 @@ impl App {
             bulk_action_ids: Vec::new(),
             bulk_folder_index: 0,
             bulk_done_return: Mode::View,
+            tag_input: String::new(),
+            tag_index: 0,
             confirm_delete_ids: Vec::new(),
             confirm_delete_return: Mode::View,
             confirm_link: None,
@@ -141,6 +149,8 @@ impl App {
             confirm_compose_action: None,
             confirm_compose_attachment_count: 0,
             confirm_compose_return: Mode::View,
+            confirm_certificate: None,
+            confirm_certificate_return: Mode::View,
             picker_mode: None,
             picker_focus: PickerFocus::Explorer,
             picker: None,
@@ -158,12 +168,17 @@ impl App {
             picker_preview_error: None,
             image_resize_prompt: None,
             imap_enabled,
+            permanent_delete,
+            gmail_labels,
             last_folder_sync: None,
             last_backfill: None,
             imap_pending: 0,
             imap_spinner: 0,
             imap_status: None,
             initial_sync_days,
+            folder_sync_days,
+            sync_poll,
+            pending_ops: PendingOps::default(),
             compose_to: String::new(),
             compose_from,
             compose_cc: String::new(),
@@ -172,6 +187,10 @@ impl App {
             compose_body: compose_buffer_from_body(ui_theme.clone(), ""),
             compose_quote: String::new(),
             compose_attachments: Vec::new(),
+            compose_pending_parts: Vec::new(),
+            compose_draft_id: None,
+            sending_draft_id: None,
+            sending_op: None,
             compose_vim_enabled,
             compose_vim_mode: if compose_vim_enabled {
                 ComposeVimMode::Normal
@@ -202,10 +221,11 @@ impl App {
         app.sort_folders();
         app.refresh_compose_address_book();
         app.refresh_compose_sender_book();
+        app.load_pending_ops();
         if app.imap_enabled {
-            let _ = app.engine.send(MailCommand::SyncAll);
-            app.imap_pending = app.imap_pending.saturating_add(2);
-            app.imap_status = Some("IMAP syncing...".to_string());
+            app.request_sync_all();
+            let _ = app.engine.send(MailCommand::StartIdle);
+            app.imap_pending = app.imap_pending.saturating_add(1);
         }
         app
     }
@@ -233,16 +253,18 @@ impl App {
             "All Mail", "INBOX", "Starred", "Sent", "Drafts", "Archive", "Spam", "Trash",
         ];
         self.store.folders.sort_by(|a, b| {
-            let a_name = canonical_folder_name(&a.name);
-            let b_name = canonical_folder_name(&b.name);
+            let a_name = folder_display_name(a);
+            let b_name = folder_display_name(b);
             let a_idx = PRIORITY.iter().position(|p| p == &a_name);
             let b_idx = PRIORITY.iter().position(|p| p == &b_name);
-            match (a_idx, b_idx) {
+            let order = match (a_idx, b_idx) {
                 (Some(ai), Some(bi)) => ai.cmp(&bi),
                 (Some(_), None) => std::cmp::Ordering::Less,
                 (None, Some(_)) => std::cmp::Ordering::Greater,
                 (None, None) => a_name.cmp(&b_name),
-            }
+            };
+            // Keep the folder that actually holds the role when names collide.
+            order.then_with(|| b.role.is_some().cmp(&a.role.is_some()))
         });
         let mut seen = HashSet::new();
         let system: HashSet<&'static str> = [
@@ -251,7 +273,7 @@ impl App {
         .into_iter()
         .collect();
         self.store.folders.retain(|f| {
-            let canonical = canonical_folder_name(&f.name);
+            let canonical = folder_display_name(f);
             if !system.contains(canonical.as_str()) {
                 return true;
             }
@@ -263,8 +285,8 @@ impl App {
         });
     }
 
-    pub(crate) fn display_folder_name(raw: &str) -> String {
-        canonical_folder_name(raw)
+    pub(crate) fn display_folder_name(folder: &Folder) -> String {
+        folder_display_name(folder)
     }
 
     pub(crate) fn restore_selection(
@@ -345,6 +367,8 @@ impl App {
             self.request_sync_selected_folder();
             self.prefetch_raw_bodies(10);
         }
+        self.poll_folders();
+        self.replay_pending_ops();
         if self.imap_pending > 0 {
             self.imap_spinner = (self.imap_spinner + 1) % IMAP_SPINNER_FRAMES.len();
             self.imap_status = Some(format!(
@@ -352,7 +376,7 @@ impl App {
                 IMAP_SPINNER_FRAMES[self.imap_spinner]
             ));
         }
-        if self.search_spec.needs_raw() {
+        if self.search_spec.needs_attachments() {
             self.prefetch_search_attachments_step(4);
         }
         if self.render_pending {
@@ -392,6 +416,8 @@ mod tests {
             bcc: String::new(),
             subject: "first".to_string(),
             body: "first".to_string(),
+            replaces: None,
+            send_op: None,
         })
         .unwrap();
 
@@ -406,6 +432,8 @@ mod tests {
                 bcc: String::new(),
                 subject: "second".to_string(),
                 body: "second".to_string(),
+                replaces: None,
+                send_op: None,
             },
         );
 
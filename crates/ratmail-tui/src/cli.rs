@@ -3,8 +3,9 @@ use clap::{Args, Parser, Subcommand};
 use super::{
     AccountConfig, CLI_SCHEMA_VERSION, RenderConfig, SearchSpec, SendConfig, SpellConfig, UiConfig,
     build_html_body, cc_from_raw, extract_display, extract_email, load_config_text,
-    mailaddrs_to_emails, normalize_ui_theme, parse_search_spec, parse_ui_palette, shell_split,
-    to_from_raw,
+    mailaddrs_to_emails, message_summary_from_imap, moved_out_of, normalize_ui_theme,
+    parse_search_spec, parse_ui_palette, pending_op_label, pending_op_parks, queue_mail_command,
+    shell_split, sync_resume_modes, to_from_raw,
 };
 
 #[path = "cli_command_handlers.rs"]
@@ -33,7 +34,8 @@ pub(crate) use cli_message_filters::{
     parse_from_addrs, parse_since_ts, spec_matches_attachments_cli, spec_matches_text_fields_cli,
 };
 pub(crate) use cli_runtime_helpers::{
-    filter_summary_to_json, output_error, output_ok, resolve_account, resolve_cli_command,
+    filter_summary_to_json, output_error, output_imap_error, output_ok, resolve_account,
+    resolve_cli_command,
 };
 pub(crate) use cli_setup::run_setup_wizard;
 
@@ -55,6 +57,7 @@ pub(crate) enum CliCommand {
     Message(MessageCmd),
     Sync(SyncCmd),
     Send(SendCmd),
+    Queue(QueueCmd),
 }
 
 #[derive(Args, Debug)]
@@ -110,6 +113,8 @@ pub(crate) struct MessagesList {
     #[arg(long)]
     unread: bool,
     #[arg(long)]
+    flagged: bool,
+    #[arg(long)]
     from: Option<String>,
     #[arg(long)]
     subject: Option<String>,
@@ -133,6 +138,9 @@ pub(crate) struct MessagesList {
     att_type: Vec<String>,
     #[arg(long)]
     fetch: bool,
+    /// Also run the query as an IMAP SEARCH and cache the matching headers.
+    #[arg(long)]
+    server: bool,
 }
 
 #[derive(Args, Debug)]
@@ -150,6 +158,8 @@ pub(crate) enum MessageCommand {
     Move(MessageMove),
     Delete(MessageDelete),
     Mark(MessageMark),
+    Flag(MessageFlag),
+    Tag(MessageTag),
 }
 
 #[derive(Args, Debug)]
@@ -232,6 +242,30 @@ pub(crate) struct MessageMark {
     unread: bool,
 }
 
+#[derive(Args, Debug)]
+pub(crate) struct MessageFlag {
+    #[arg(long)]
+    account: Option<String>,
+    #[arg(long)]
+    id: i64,
+    #[arg(long)]
+    unflag: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct MessageTag {
+    #[arg(long)]
+    account: Option<String>,
+    #[arg(long)]
+    id: i64,
+    /// Keyword to add; repeat for several.
+    #[arg(long)]
+    add: Vec<String>,
+    /// Keyword to remove; repeat for several.
+    #[arg(long)]
+    remove: Vec<String>,
+}
+
 #[derive(Args, Debug)]
 pub(crate) struct SyncCmd {
     #[arg(long)]
@@ -270,6 +304,46 @@ pub(crate) struct SendCmd {
     timeout_secs: u64,
 }
 
+#[derive(Args, Debug)]
+pub(crate) struct QueueCmd {
+    #[command(subcommand)]
+    command: QueueCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum QueueCommand {
+    List(QueueList),
+    /// Replay queued operations in order, stopping at the first failure.
+    Retry(QueueRetry),
+    /// Forget an operation; its local change is kept.
+    Drop(QueueDrop),
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct QueueList {
+    #[arg(long)]
+    account: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct QueueRetry {
+    #[arg(long)]
+    account: Option<String>,
+    /// Only replay this operation.
+    #[arg(long)]
+    id: Option<i64>,
+    #[arg(long, default_value_t = 60)]
+    timeout_secs: u64,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct QueueDrop {
+    #[arg(long)]
+    account: Option<String>,
+    #[arg(long)]
+    id: i64,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct CliConfig {
     enabled: bool,
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::io::{self, IsTerminal};
 use std::path::Path;
 use std::sync::Arc;
@@ -8,19 +9,25 @@ use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use mime_guess::MimeGuess;
 use ratmail_content::{extract_attachment_data, extract_attachments, extract_display};
-use ratmail_core::{AttachmentMeta, DEFAULT_TEXT_WIDTH, Folder, SqliteMailStore};
-use ratmail_mail::{MailCommand, MailEngine, MailEvent, OutgoingAttachment};
+use ratmail_core::{
+    AttachmentMeta, DEFAULT_TEXT_WIDTH, Folder, FolderRole, MessageSummary, SqliteMailStore,
+};
+use ratmail_mail::{
+    ImapConfig, ImapErrorContext, ImapErrorKind, MailCommand, MailEngine, MailEvent,
+    OutgoingAttachment, is_valid_gmail_label, is_valid_imap_keyword,
+};
 use serde_json::{Value as JsonValue, json};
 
 use super::{
     AccountConfig, AccountsCommand, CliCommand, FoldersCommand, MessageCommand, MessagesCommand,
-    account_id_for, allowed_fields, build_html_body, cc_from_raw, cli_allows_account,
+    QueueCommand, account_id_for, allowed_fields, build_html_body, cc_from_raw, cli_allows_account,
     cli_allows_attachments, cli_allows_body, cli_allows_command, cli_allows_delete,
     cli_allows_folder, cli_allows_from, cli_allows_mark, cli_allows_move, cli_allows_raw,
     cli_allows_send, filter_summary_to_json, load_cli_config, load_send_config, map_folder_names,
-    maybe_fetch_raw, output_error, output_ok, parse_before_ts, parse_from_addrs, parse_search_spec,
-    parse_since_ts, resolve_account, run_setup_wizard, spec_matches_attachments_cli,
-    spec_matches_text_fields_cli, to_from_raw,
+    maybe_fetch_raw, message_summary_from_imap, moved_out_of, output_error, output_imap_error,
+    output_ok, parse_before_ts, parse_from_addrs, parse_search_spec, parse_since_ts,
+    pending_op_label, pending_op_parks, queue_mail_command, resolve_account, run_setup_wizard,
+    spec_matches_attachments_cli, spec_matches_text_fields_cli, sync_resume_modes, to_from_raw,
 };
 
 pub(crate) fn run_cli(
@@ -154,10 +161,12 @@ pub(crate) fn run_cli(
                 }
                 let since_ts = spec.since_ts;
                 let unread = if args.unread { Some(true) } else { None };
-                let messages = rt.block_on(store.list_messages(
+                let flagged = if args.flagged { Some(true) } else { None };
+                let mut messages = rt.block_on(store.list_messages(
                     account_id,
                     folder_id,
                     unread,
+                    flagged,
                     since_ts,
                     Some(args.limit as i64),
                 ))?;
@@ -165,6 +174,56 @@ pub(crate) fn run_cli(
                     .block_on(store.list_folders(account_id))
                     .unwrap_or_default();
                 let folder_map = map_folder_names(&folders);
+                let mut server_hits: HashSet<i64> = HashSet::new();
+                if args.server {
+                    let Some(imap) = account.imap.as_ref() else {
+                        return output_error("IMAP not configured");
+                    };
+                    let Some(query) = spec.imap_query() else {
+                        return output_error("Query has no server-searchable terms");
+                    };
+                    for folder in &folders {
+                        if folder_id.is_some_and(|id| id != folder.id)
+                            || !cli_allows_folder(&config, &folder.name)
+                        {
+                            continue;
+                        }
+                        let found = ratmail_mail::search_imap_folder(
+                            imap,
+                            &folder.name,
+                            &query,
+                            args.limit,
+                        )?;
+                        let uids: Vec<u32> = found.iter().map(|m| m.uid).collect();
+                        let summaries: Vec<_> = found
+                            .into_iter()
+                            .map(|m| MessageSummary {
+                                folder_id: folder.id,
+                                ..message_summary_from_imap(m)
+                            })
+                            .collect();
+                        rt.block_on(
+                            store.upsert_folder_messages_append(account_id, folder.id, &summaries),
+                        )?;
+                        let hits = rt.block_on(store.list_messages_by_uids(folder.id, &uids))?;
+                        for hit in hits {
+                            if (args.unread && !hit.unread) || (args.flagged && !hit.flagged) {
+                                continue;
+                            }
+                            server_hits.insert(hit.id);
+                            if !messages.iter().any(|m| m.id == hit.id) {
+                                messages.push(hit);
+                            }
+                        }
+                    }
+                    messages.sort_by_key(|m| {
+                        std::cmp::Reverse(mailparse::dateparse(&m.date).unwrap_or(0))
+                    });
+                    messages.truncate(args.limit);
+                }
+                if spec.sort_by_size {
+                    messages.sort_by_key(|m| std::cmp::Reverse(m.size));
+                }
                 if spec.needs_attachments() && !cli_allows_attachments(&config) {
                     return output_error("Attachments access not allowed");
                 }
@@ -183,10 +242,13 @@ pub(crate) fn run_cli(
                     {
                         continue;
                     }
-                    let mut to_val: Option<String> = None;
-                    let mut cc_val: Option<String> = None;
+                    let mut to_val = Some(message.to.clone());
+                    let mut cc_val = Some(message.cc.clone());
                     let mut attachments: Option<Vec<AttachmentMeta>> = None;
-                    if spec.needs_raw() {
+                    // Rows synced before recipients were stored only have them in the body.
+                    let legacy_recipients =
+                        !spec.to.is_empty() && message.to.is_empty() && message.cc.is_empty();
+                    if spec.needs_attachments() || legacy_recipients {
                         let raw = maybe_fetch_raw(
                             rt,
                             &store,
@@ -197,7 +259,7 @@ pub(crate) fn run_cli(
                             args.fetch,
                         )?;
                         let Some(raw) = raw else { continue };
-                        if !spec.to.is_empty() {
+                        if legacy_recipients {
                             to_val = to_from_raw(&raw);
                             cc_val = cc_from_raw(&raw);
                         }
@@ -209,12 +271,14 @@ pub(crate) fn run_cli(
                             }
                         }
                     }
-                    if !spec_matches_text_fields_cli(
-                        &spec,
-                        &message,
-                        to_val.as_deref(),
-                        cc_val.as_deref(),
-                    ) {
+                    if !server_hits.contains(&message.id)
+                        && !spec_matches_text_fields_cli(
+                            &spec,
+                            &message,
+                            to_val.as_deref(),
+                            cc_val.as_deref(),
+                        )
+                    {
                         continue;
                     }
                     if spec.needs_attachments() {
@@ -390,6 +454,9 @@ pub(crate) fn run_cli(
                 let Some(attachment) = extract_attachment_data(&raw, args.index)? else {
                     return output_error("Attachment not found");
                 };
+                if attachment.omitted_section.is_some() {
+                    return output_error("Attachment not downloaded (use --fetch)");
+                }
                 std::fs::write(&args.path, &attachment.data)?;
                 return output_ok(json!({
                     "id": summary.id,
@@ -539,13 +606,41 @@ pub(crate) fn run_cli(
                     summary.imap_uid,
                     folder_map.get(&summary.folder_id),
                 ) {
-                    let (engine, _events) =
+                    let (engine, mut events) =
                         rt.block_on(async { MailEngine::start(None, Some(imap)) });
-                    let _ = engine.send(MailCommand::MoveMessages {
-                        folder_name: src_name.clone(),
-                        target_folder: args.folder.clone(),
-                        uids: vec![uid],
-                    });
+                    let op_id = rt.block_on(queue_mail_command(
+                        &store,
+                        account_id,
+                        &engine,
+                        MailCommand::MoveMessages {
+                            folder_name: src_name.clone(),
+                            target_folder: args.folder.clone(),
+                            uids: vec![uid],
+                        },
+                    ))?;
+                    match wait_for_pending_op(rt, &store, &mut events, op_id, PENDING_OP_TIMEOUT)? {
+                        Some((seen, None)) => {
+                            if let Some(moved) = moved_uids(seen) {
+                                rt.block_on(store.remap_moved_uids(
+                                    summary.folder_id,
+                                    target_id,
+                                    &moved,
+                                ))?;
+                            }
+                        }
+                        Some((_, Some(failure))) => {
+                            if failure.parked {
+                                rt.block_on(
+                                    store.restore_moved_messages(summary.folder_id, &[uid]),
+                                )?;
+                            }
+                            return output_imap_error(
+                                &failure.message("Move failed", op_id),
+                                failure.kind,
+                            );
+                        }
+                        None => {}
+                    }
                 }
                 return output_ok(json!({ "id": summary.id, "moved_to": args.folder }));
             }
@@ -574,20 +669,70 @@ pub(crate) fn run_cli(
                         return output_error("Folder not allowed");
                     }
                 }
-                rt.block_on(store.delete_messages(&[summary.id]))?;
+                let source = folders.iter().find(|f| f.id == summary.folder_id);
+                let to_trash = !account
+                    .imap
+                    .as_ref()
+                    .is_some_and(|imap| imap.permanent_delete)
+                    && source.is_some_and(|f| f.role != Some(FolderRole::Trash));
+                let trash = if to_trash {
+                    let trash_id =
+                        rt.block_on(store.ensure_folder_for_role(account_id, FolderRole::Trash))?;
+                    rt.block_on(store.move_messages(&[summary.id], trash_id))?;
+                    let name = rt
+                        .block_on(store.get_folder(trash_id))?
+                        .map(|f| f.name)
+                        .unwrap_or_else(|| FolderRole::Trash.default_name().to_string());
+                    Some((trash_id, name))
+                } else {
+                    rt.block_on(store.delete_messages(&[summary.id]))?;
+                    None
+                };
+                let trash_folder = trash.as_ref().map(|(_, name)| name.clone());
                 if let (Some(imap), Some(uid), Some(src_name)) = (
                     account.imap.clone(),
                     summary.imap_uid,
                     folder_map.get(&summary.folder_id),
                 ) {
-                    let (engine, _events) =
+                    let (engine, mut events) =
                         rt.block_on(async { MailEngine::start(None, Some(imap)) });
-                    let _ = engine.send(MailCommand::DeleteMessages {
-                        folder_name: src_name.clone(),
-                        uids: vec![uid],
-                    });
+                    let op_id = rt.block_on(queue_mail_command(
+                        &store,
+                        account_id,
+                        &engine,
+                        MailCommand::DeleteMessages {
+                            folder_name: src_name.clone(),
+                            uids: vec![uid],
+                            trash_folder: trash_folder.clone(),
+                        },
+                    ))?;
+                    match wait_for_pending_op(rt, &store, &mut events, op_id, PENDING_OP_TIMEOUT)? {
+                        Some((seen, None)) => {
+                            if let (Some((trash_id, _)), Some(moved)) = (&trash, moved_uids(seen)) {
+                                rt.block_on(store.remap_moved_uids(
+                                    summary.folder_id,
+                                    *trash_id,
+                                    &moved,
+                                ))?;
+                            }
+                        }
+                        Some((_, Some(failure))) => {
+                            if failure.parked && trash.is_some() {
+                                rt.block_on(
+                                    store.restore_moved_messages(summary.folder_id, &[uid]),
+                                )?;
+                            }
+                            return output_imap_error(
+                                &failure.message("Delete failed", op_id),
+                                failure.kind,
+                            );
+                        }
+                        None => {}
+                    }
                 }
-                return output_ok(json!({ "id": summary.id, "deleted": true }));
+                return output_ok(
+                    json!({ "id": summary.id, "deleted": true, "moved_to": trash_folder }),
+                );
             }
             MessageCommand::Mark(args) => {
                 if !cli_allows_command(&config, "message.mark", true) || !cli_allows_mark(&config) {
@@ -617,16 +762,193 @@ pub(crate) fn run_cli(
                     }
                 }
                 rt.block_on(store.set_message_unread(summary.id, unread))?;
-                if let Some(imap) = account.imap.clone() {
-                    let (engine, _events) =
+                if let (Some(imap), Some(uid), Some(folder_name)) = (
+                    account.imap.clone(),
+                    summary.imap_uid,
+                    folder_map.get(&summary.folder_id),
+                ) {
+                    let (engine, mut events) =
                         rt.block_on(async { MailEngine::start(None, Some(imap)) });
-                    let _ = engine.send(MailCommand::SetFlag {
-                        message_id: summary.id,
-                        seen: !unread,
-                    });
+                    let op_id = rt.block_on(queue_mail_command(
+                        &store,
+                        account_id,
+                        &engine,
+                        MailCommand::SetFlag {
+                            message_id: summary.id,
+                            folder_name: folder_name.clone(),
+                            uid,
+                            seen: !unread,
+                        },
+                    ))?;
+                    match wait_for_pending_op(rt, &store, &mut events, op_id, PENDING_OP_TIMEOUT)? {
+                        Some((_, None)) => {}
+                        Some((_, Some(failure))) => {
+                            if failure.parked {
+                                rt.block_on(store.set_message_unread(summary.id, summary.unread))?;
+                            }
+                            return output_imap_error(
+                                &failure.message("Mark failed", op_id),
+                                failure.kind,
+                            );
+                        }
+                        None => return output_error(&queued_timeout("Mark", op_id)),
+                    }
                 }
                 return output_ok(json!({ "id": summary.id, "unread": unread }));
             }
+            MessageCommand::Flag(args) => {
+                if !cli_allows_command(&config, "message.flag", true) || !cli_allows_mark(&config) {
+                    return output_error("Command not allowed");
+                }
+                let flagged = !args.unflag;
+                let account = resolve_account(&config, accounts, args.account.as_deref())
+                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                if !cli_allows_account(&config, &account.name) {
+                    return output_error("Account not allowed");
+                }
+                let store = rt.block_on(SqliteMailStore::connect(&account.db_path))?;
+                let account_id = account_id_for(rt, &store, &account.name);
+                let summary = rt
+                    .block_on(store.get_message_summary(args.id))?
+                    .ok_or_else(|| anyhow::anyhow!("Message not found"))?;
+                let folders = rt
+                    .block_on(store.list_folders(account_id))
+                    .unwrap_or_default();
+                let folder_map = map_folder_names(&folders);
+                if let Some(folder_name) = folder_map.get(&summary.folder_id)
+                    && !cli_allows_folder(&config, folder_name)
+                {
+                    return output_error("Folder not allowed");
+                }
+                rt.block_on(store.set_message_flagged(summary.id, flagged))?;
+                if let (Some(imap), Some(uid), Some(folder_name)) = (
+                    account.imap.clone(),
+                    summary.imap_uid,
+                    folder_map.get(&summary.folder_id),
+                ) {
+                    let (engine, mut events) =
+                        rt.block_on(async { MailEngine::start(None, Some(imap)) });
+                    let op_id = rt.block_on(queue_mail_command(
+                        &store,
+                        account_id,
+                        &engine,
+                        MailCommand::SetFlagged {
+                            message_id: summary.id,
+                            folder_name: folder_name.clone(),
+                            uid,
+                            flagged,
+                        },
+                    ))?;
+                    match wait_for_pending_op(rt, &store, &mut events, op_id, PENDING_OP_TIMEOUT)? {
+                        Some((_, None)) => {}
+                        Some((_, Some(failure))) => {
+                            if failure.parked {
+                                rt.block_on(
+                                    store.set_message_flagged(summary.id, summary.flagged),
+                                )?;
+                            }
+                            return output_imap_error(
+                                &failure.message("Flag failed", op_id),
+                                failure.kind,
+                            );
+                        }
+                        None => return output_error(&queued_timeout("Flag", op_id)),
+                    }
+                }
+                output_ok(json!({ "id": summary.id, "flagged": flagged }))
+            }
+            MessageCommand::Tag(args) => {
+                if !cli_allows_command(&config, "message.tag", true) || !cli_allows_mark(&config) {
+                    return output_error("Command not allowed");
+                }
+                if args.add.is_empty() && args.remove.is_empty() {
+                    return output_error("Nothing to do: pass --add or --remove");
+                }
+                let account = resolve_account(&config, accounts, args.account.as_deref())
+                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                if !cli_allows_account(&config, &account.name) {
+                    return output_error("Account not allowed");
+                }
+                // Gmail labels are sent quoted and may contain spaces.
+                let gmail = account.imap.as_ref().is_some_and(ImapConfig::is_gmail);
+                if let Some(invalid) = args.add.iter().chain(args.remove.iter()).find(|k| {
+                    if gmail {
+                        !is_valid_gmail_label(k)
+                    } else {
+                        !is_valid_imap_keyword(k)
+                    }
+                }) {
+                    return output_error(&format!("Invalid keyword: {}", invalid));
+                }
+                let store = rt.block_on(SqliteMailStore::connect(&account.db_path))?;
+                let account_id = account_id_for(rt, &store, &account.name);
+                let summary = rt
+                    .block_on(store.get_message_summary(args.id))?
+                    .ok_or_else(|| anyhow::anyhow!("Message not found"))?;
+                let folders = rt
+                    .block_on(store.list_folders(account_id))
+                    .unwrap_or_default();
+                let folder_map = map_folder_names(&folders);
+                if let Some(folder_name) = folder_map.get(&summary.folder_id)
+                    && !cli_allows_folder(&config, folder_name)
+                {
+                    return output_error("Folder not allowed");
+                }
+                let add: Vec<String> = args
+                    .add
+                    .iter()
+                    .filter(|k| !summary.keywords.contains(k) && !args.remove.contains(k))
+                    .cloned()
+                    .collect();
+                let remove: Vec<String> = args
+                    .remove
+                    .iter()
+                    .filter(|k| summary.keywords.contains(k))
+                    .cloned()
+                    .collect();
+                rt.block_on(store.update_message_keywords(summary.id, &add, &remove))?;
+                if let (Some(imap), Some(uid), Some(folder_name)) = (
+                    account.imap.clone(),
+                    summary.imap_uid,
+                    folder_map.get(&summary.folder_id),
+                ) && !(add.is_empty() && remove.is_empty())
+                {
+                    let (engine, mut events) =
+                        rt.block_on(async { MailEngine::start(None, Some(imap)) });
+                    let op_id = rt.block_on(queue_mail_command(
+                        &store,
+                        account_id,
+                        &engine,
+                        MailCommand::SetKeywords {
+                            message_id: summary.id,
+                            folder_name: folder_name.clone(),
+                            uid,
+                            add: add.clone(),
+                            remove: remove.clone(),
+                        },
+                    ))?;
+                    match wait_for_pending_op(rt, &store, &mut events, op_id, PENDING_OP_TIMEOUT)? {
+                        Some((_, None)) => {}
+                        Some((_, Some(failure))) => {
+                            if failure.parked {
+                                rt.block_on(
+                                    store.update_message_keywords(summary.id, &remove, &add),
+                                )?;
+                            }
+                            return output_imap_error(
+                                &failure.message("Tag failed", op_id),
+                                failure.kind,
+                            );
+                        }
+                        None => return output_error(&queued_timeout("Tag", op_id)),
+                    }
+                }
+                let keywords = rt
+                    .block_on(store.get_message_summary(summary.id))?
+                    .map(|s| s.keywords)
+                    .unwrap_or_default();
+                output_ok(json!({ "id": summary.id, "keywords": keywords }))
+            }
         },
         CliCommand::Sync(cmd) => {
             if !cli_allows_command(&config, "sync", true) {
@@ -666,8 +988,11 @@ pub(crate) fn run_cli(
                                 .unwrap_or(90),
                         ),
                     }
-                } else if let Some(uid) = state.and_then(|s| s.last_seen_uid).map(|v| v as u32) {
-                    ratmail_mail::SyncMode::Incremental { last_seen_uid: uid }
+                } else if let Some(state) = state.filter(|s| s.last_seen_uid.is_some()) {
+                    ratmail_mail::SyncMode::Incremental {
+                        last_seen_uid: state.last_seen_uid.unwrap_or(0) as u32,
+                        highest_modseq: state.highest_modseq.map(|v| v as u64),
+                    }
                 } else {
                     ratmail_mail::SyncMode::Initial {
                         days: cmd.days.unwrap_or(
@@ -694,8 +1019,11 @@ pub(crate) fn run_cli(
                                 MailEvent::ImapMessages { .. } => {
                                     return output_ok(json!({ "synced": true }));
                                 }
-                                MailEvent::ImapError { reason, .. } => {
-                                    return output_error(&format!("Sync failed: {}", reason));
+                                MailEvent::ImapError { kind, reason, .. } => {
+                                    return output_imap_error(
+                                        &format!("Sync failed: {}", reason),
+                                        Some(kind),
+                                    );
                                 }
                                 _ => {}
                             }
@@ -706,20 +1034,40 @@ pub(crate) fn run_cli(
                 }
                 return output_ok(json!({ "queued": true }));
             }
-            let _ = engine.send(MailCommand::SyncAll);
+            let store = rt.block_on(SqliteMailStore::connect(&account.db_path))?;
+            let account_id = account_id_for(rt, &store, &account.name);
+            let folders = rt
+                .block_on(store.list_folders(account_id))
+                .unwrap_or_default();
+            let resume = sync_resume_modes(rt, &store, &folders);
+            let _ = engine.send(MailCommand::SyncAll { resume });
             if cmd.wait {
                 let deadline = Instant::now() + Duration::from_secs(cmd.timeout_secs);
+                let mut failed = Vec::new();
                 loop {
                     if Instant::now() > deadline {
                         return output_error("Sync timeout");
                     }
                     if let Ok(event) = events.try_recv() {
                         match event {
-                            MailEvent::ImapMessages { .. } => {
-                                return output_ok(json!({ "synced": true }));
+                            MailEvent::FolderSyncProgress {
+                                folder_name: None,
+                                total,
+                                ..
+                            } => {
+                                return output_ok(
+                                    json!({ "synced": true, "folders": total, "failed": failed }),
+                                );
                             }
-                            MailEvent::ImapError { reason, .. } => {
-                                return output_error(&format!("Sync failed: {}", reason));
+                            MailEvent::ImapError {
+                                context: ImapErrorContext::SyncFolder { folder_name },
+                                ..
+                            } => failed.push(folder_name),
+                            MailEvent::ImapError { kind, reason, .. } => {
+                                return output_imap_error(
+                                    &format!("Sync failed: {}", reason),
+                                    Some(kind),
+                                );
                             }
                             _ => {}
                         }
@@ -757,9 +1105,21 @@ pub(crate) fn run_cli(
                     data,
                 })
                 .collect::<Vec<_>>();
-            let (engine, mut events) = rt.block_on(async { MailEngine::start(Some(smtp), None) });
+            // The Sent copy needs IMAP; without it the engine only talks SMTP.
+            let imap = account.imap.clone().filter(|_| smtp.save_sent);
+            let store = rt.block_on(SqliteMailStore::connect(&account.db_path))?;
+            let account_id = account_id_for(rt, &store, &account.name);
+            let sent_folder = if imap.is_some() {
+                rt.block_on(store.list_folders(account_id))?
+                    .into_iter()
+                    .find(|f| f.role == Some(FolderRole::Sent))
+                    .map(|f| f.name)
+            } else {
+                None
+            };
+            let save_sent = imap.is_some();
             let body_html = build_html_body(&cmd.body, &send_config);
-            let _ = engine.send(MailCommand::SendMessage {
+            let command = MailCommand::SendMessage {
                 from: None,
                 to: cmd.to,
                 cc: cmd.cc.unwrap_or_default(),
@@ -768,28 +1128,403 @@ pub(crate) fn run_cli(
                 body: cmd.body,
                 body_html,
                 attachments,
-            });
-            if cmd.wait {
-                let start = Instant::now();
-                while start.elapsed() < Duration::from_secs(cmd.timeout_secs) {
-                    if let Ok(event) = events.try_recv() {
-                        match event {
-                            MailEvent::SendCompleted => {
+                sent_folder,
+            };
+            if !cmd.wait {
+                // Exiting would cut the send short; the TUI or `queue retry`
+                // sends it instead.
+                let kind = command.pending_kind().unwrap_or("send");
+                let op_id = rt.block_on(store.enqueue_pending_op(
+                    account_id,
+                    kind,
+                    &serde_json::to_string(&command)?,
+                ))?;
+                return output_ok(json!({ "queued": true, "op_id": op_id }));
+            }
+            let (engine, mut events) = rt.block_on(async { MailEngine::start(Some(smtp), imap) });
+            let op_id = rt.block_on(queue_mail_command(&store, account_id, &engine, command))?;
+            let start = Instant::now();
+            let mut sent = false;
+            while start.elapsed() < Duration::from_secs(cmd.timeout_secs) {
+                if let Ok(event) = events.try_recv() {
+                    match event {
+                        MailEvent::PendingOpFinished {
+                            op_id: id,
+                            error,
+                            kind,
+                        } if id == op_id => {
+                            if let Some(reason) = error {
+                                let failure = OpFailure::record(rt, &store, op_id, reason, kind)?;
+                                return output_imap_error(
+                                    &failure.message("Send failed", op_id),
+                                    failure.kind,
+                                );
+                            }
+                            rt.block_on(store.delete_pending_op(op_id))?;
+                            if !save_sent {
                                 return output_ok(json!({ "sent": true }));
                             }
-                            MailEvent::SendFailed { reason } => {
-                                return output_error(&format!("Send failed: {}", reason));
+                            sent = true;
+                        }
+                        MailEvent::SentSaved {
+                            folder_name,
+                            uid,
+                            raw,
+                        } => {
+                            let folder_id = match rt
+                                .block_on(store.folder_id_by_name(account_id, &folder_name))?
+                            {
+                                Some(id) => id,
+                                None => rt.block_on(
+                                    store.ensure_folder_for_role(account_id, FolderRole::Sent),
+                                )?,
+                            };
+                            rt.block_on(store.save_sent_message(account_id, folder_id, uid, &raw))?;
+                            if !sent {
+                                // The copy can arrive before the send is reported.
+                                rt.block_on(store.delete_pending_op(op_id))?;
                             }
-                            _ => {}
+                            return output_ok(json!({
+                                "sent": true,
+                                "saved_to": folder_name,
+                            }));
                         }
-                    } else {
-                        std::thread::sleep(Duration::from_millis(50));
+                        MailEvent::ImapError { kind, reason, .. } if sent => {
+                            return output_ok(json!({
+                                "sent": true,
+                                "saved_to": null,
+                                "save_error": reason,
+                                "save_error_kind": kind,
+                            }));
+                        }
+                        _ => {}
                     }
+                } else {
+                    std::thread::sleep(Duration::from_millis(50));
                 }
-                return output_error("Send timeout");
             }
-            return output_ok(json!({ "queued": true }));
+            if sent {
+                return output_ok(json!({ "sent": true, "saved_to": null }));
+            }
+            return output_error(&queued_timeout("Send", op_id));
         }
+        CliCommand::Queue(cmd) => match cmd.command {
+            QueueCommand::List(args) => {
+                if !cli_allows_command(&config, "queue.list", false) {
+                    return output_error("Command not allowed");
+                }
+                let account = resolve_account(&config, accounts, args.account.as_deref())
+                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                if !cli_allows_account(&config, &account.name) {
+                    return output_error("Account not allowed");
+                }
+                let store = rt.block_on(SqliteMailStore::connect(&account.db_path))?;
+                let account_id = account_id_for(rt, &store, &account.name);
+                let ops = rt.block_on(store.list_pending_ops(account_id))?;
+                let out: Vec<JsonValue> = ops
+                    .iter()
+                    .map(|op| {
+                        json!({
+                            "id": op.id,
+                            "kind": op.kind,
+                            "summary": pending_op_label(op),
+                            "attempts": op.attempts,
+                            "last_error": op.last_error,
+                            "failed": op.failed,
+                            "created_ts": op.created_ts,
+                            "last_attempt_ts": op.last_attempt_ts,
+                        })
+                    })
+                    .collect();
+                output_ok(json!(out))
+            }
+            QueueCommand::Retry(args) => {
+                if !cli_allows_command(&config, "queue.retry", true) {
+                    return output_error("Command not allowed");
+                }
+                let account = resolve_account(&config, accounts, args.account.as_deref())
+                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                if !cli_allows_account(&config, &account.name) {
+                    return output_error("Account not allowed");
+                }
+                let store = rt.block_on(SqliteMailStore::connect(&account.db_path))?;
+                let account_id = account_id_for(rt, &store, &account.name);
+                let mut ops = rt.block_on(store.list_pending_ops(account_id))?;
+                if let Some(id) = args.id {
+                    ops.retain(|op| op.id == id);
+                    if ops.is_empty() {
+                        return output_error("Operation not found");
+                    }
+                } else {
+                    // Refused ops are only retried by id.
+                    ops.retain(|op| !op.failed);
+                }
+                let mut commands = Vec::with_capacity(ops.len());
+                for op in &ops {
+                    let command = serde_json::from_str::<MailCommand>(&op.payload)
+                        .map_err(|e| anyhow::anyhow!("op {} unreadable: {}", op.id, e))?;
+                    let allowed = match &command {
+                        MailCommand::MoveMessages { .. } => cli_allows_move(&config),
+                        MailCommand::DeleteMessages { .. } => cli_allows_delete(&config),
+                        MailCommand::SendMessage { .. } => cli_allows_send(&config),
+                        _ => cli_allows_mark(&config),
+                    };
+                    if !allowed {
+                        return output_error(&format!("Operation {} not allowed", op.id));
+                    }
+                    commands.push((op.id, op.draft_id, command));
+                }
+                let (engine, mut events) = rt.block_on(async {
+                    MailEngine::start(account.smtp.clone(), account.imap.clone())
+                });
+                let timeout = Duration::from_secs(args.timeout_secs);
+                let mut replayed = Vec::new();
+                let mut parked = Vec::new();
+                let mut failed = JsonValue::Null;
+                for (op_id, draft_id, command) in commands {
+                    let _ = engine.send(MailCommand::Pending {
+                        op_id,
+                        command: Box::new(command.clone()),
+                    });
+                    match wait_for_pending_op(rt, &store, &mut events, op_id, timeout)? {
+                        Some((seen, None)) => {
+                            for event in seen {
+                                apply_replayed_event(rt, &store, account_id, event)?;
+                            }
+                            if let Some(draft_id) = draft_id {
+                                discard_sent_draft(rt, &store, account_id, &engine, draft_id)?;
+                            }
+                            replayed.push(op_id);
+                        }
+                        // Refused for good; the ops behind it do not depend on it.
+                        Some((_, Some(failure))) if failure.parked => {
+                            restore_moved(rt, &store, account_id, &command)?;
+                            parked.push(json!({
+                                "id": op_id,
+                                "error": failure.reason,
+                                "error_kind": failure.kind,
+                            }));
+                        }
+                        Some((_, Some(failure))) => {
+                            failed = json!({
+                                "id": op_id,
+                                "error": failure.reason,
+                                "error_kind": failure.kind,
+                            });
+                            break;
+                        }
+                        None => {
+                            failed = json!({ "id": op_id, "error": "timeout" });
+                            break;
+                        }
+                    }
+                }
+                let remaining = rt.block_on(store.list_pending_ops(account_id))?.len();
+                output_ok(json!({
+                    "replayed": replayed,
+                    "parked": parked,
+                    "failed": failed,
+                    "remaining": remaining,
+                }))
+            }
+            QueueCommand::Drop(args) => {
+                if !cli_allows_command(&config, "queue.drop", true) {
+                    return output_error("Command not allowed");
+                }
+                let account = resolve_account(&config, accounts, args.account.as_deref())
+                    .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+                if !cli_allows_account(&config, &account.name) {
+                    return output_error("Account not allowed");
+                }
+                let store = rt.block_on(SqliteMailStore::connect(&account.db_path))?;
+                let account_id = account_id_for(rt, &store, &account.name);
+                let Some(op) = rt
+                    .block_on(store.list_pending_ops(account_id))?
+                    .into_iter()
+                    .find(|op| op.id == args.id)
+                else {
+                    return output_error("Operation not found");
+                };
+                if !rt.block_on(store.delete_pending_op(args.id))? {
+                    return output_error("Operation not found");
+                }
+                if let Ok(command) = serde_json::from_str::<MailCommand>(&op.payload) {
+                    restore_moved(rt, &store, account_id, &command)?;
+                }
+                output_ok(json!({ "id": args.id, "dropped": true }))
+            }
+        },
         CliCommand::Setup(_) => unreachable!("setup handled before dispatch"),
     }
 }
+
+/// How long mutating message commands wait for the server.
+const PENDING_OP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A queued op the engine reported as failed.
+struct OpFailure {
+    reason: String,
+    kind: Option<ImapErrorKind>,
+    /// The server refused it: it is parked in the queue, not retried.
+    parked: bool,
+}
+
+impl OpFailure {
+    fn record(
+        rt: &tokio::runtime::Runtime,
+        store: &SqliteMailStore,
+        op_id: i64,
+        reason: String,
+        kind: Option<ImapErrorKind>,
+    ) -> Result<Self> {
+        let parked = pending_op_parks(kind);
+        rt.block_on(store.record_pending_op_failure(op_id, &reason, parked))?;
+        Ok(Self {
+            reason,
+            kind,
+            parked,
+        })
+    }
+
+    fn message(&self, action: &str, op_id: i64) -> String {
+        if self.parked {
+            format!(
+                "{}: {}; kept as failed op {} (queue retry --id or queue drop)",
+                action, self.reason, op_id
+            )
+        } else {
+            format!(
+                "{}: {}; queued for retry as op {}",
+                action, self.reason, op_id
+            )
+        }
+    }
+}
+
+/// Puts back what a refused or dropped move took out of its folder locally.
+fn restore_moved(
+    rt: &tokio::runtime::Runtime,
+    store: &SqliteMailStore,
+    account_id: i64,
+    command: &MailCommand,
+) -> Result<()> {
+    if let Some((folder_name, uids)) = moved_out_of(command)
+        && let Some(folder_id) = rt.block_on(store.folder_id_by_name(account_id, folder_name))?
+    {
+        rt.block_on(store.restore_moved_messages(folder_id, uids))?;
+    }
+    Ok(())
+}
+
+/// Waits for the engine to answer queued op `op_id` and records the outcome
+/// in the queue. Returns the events seen before the answer and the failure;
+/// `None` means it timed out and the op stays queued.
+fn wait_for_pending_op(
+    rt: &tokio::runtime::Runtime,
+    store: &SqliteMailStore,
+    events: &mut tokio::sync::mpsc::Receiver<MailEvent>,
+    op_id: i64,
+    timeout: Duration,
+) -> Result<Option<(Vec<MailEvent>, Option<OpFailure>)>> {
+    let deadline = Instant::now() + timeout;
+    let mut seen = Vec::new();
+    while Instant::now() < deadline {
+        match events.try_recv() {
+            Ok(MailEvent::PendingOpFinished {
+                op_id: id,
+                error,
+                kind,
+            }) if id == op_id => {
+                let failure = match error {
+                    None => {
+                        rt.block_on(store.delete_pending_op(op_id))?;
+                        None
+                    }
+                    Some(reason) => Some(OpFailure::record(rt, store, op_id, reason, kind)?),
+                };
+                return Ok(Some((seen, failure)));
+            }
+            Ok(event) => seen.push(event),
+            Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => return Ok(None),
+            Err(tokio::sync::mpsc::error::TryRecvError::Empty) => {
+                std::thread::sleep(Duration::from_millis(50))
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn queued_timeout(action: &str, op_id: i64) -> String {
+    format!("{}: timeout; queued for retry as op {}", action, op_id)
+}
+
+/// Drops the draft that kept a queued send once the send went out.
+fn discard_sent_draft(
+    rt: &tokio::runtime::Runtime,
+    store: &SqliteMailStore,
+    account_id: i64,
+    engine: &MailEngine,
+    draft_id: i64,
+) -> Result<()> {
+    let Some(draft) = rt.block_on(store.get_message_summary(draft_id))? else {
+        return Ok(());
+    };
+    rt.block_on(store.delete_messages(&[draft.id]))?;
+    if let Some(uid) = draft.imap_uid
+        && let Some(folder) = rt.block_on(store.get_folder(draft.folder_id))?
+    {
+        rt.block_on(queue_mail_command(
+            store,
+            account_id,
+            engine,
+            MailCommand::DeleteMessages {
+                folder_name: folder.name,
+                uids: vec![uid],
+                trash_folder: None,
+            },
+        ))?;
+    }
+    Ok(())
+}
+
+/// Old to new UIDs reported by a finished move, if the server sent them.
+fn moved_uids(events: Vec<MailEvent>) -> Option<Vec<(u32, Option<u32>)>> {
+    events.into_iter().find_map(|event| match event {
+        MailEvent::MessagesMoved { uids, .. } if !uids.is_empty() => Some(uids),
+        _ => None,
+    })
+}
+
+/// Stores what a replayed operation reported, as the TUI would.
+fn apply_replayed_event(
+    rt: &tokio::runtime::Runtime,
+    store: &SqliteMailStore,
+    account_id: i64,
+    event: MailEvent,
+) -> Result<()> {
+    match event {
+        MailEvent::MessagesMoved {
+            folder_name,
+            target_folder,
+            uids,
+        } if !uids.is_empty() => {
+            if let Some(source_id) =
+                rt.block_on(store.folder_id_by_name(account_id, &folder_name))?
+                && let Some(folder_id) =
+                    rt.block_on(store.folder_id_by_name(account_id, &target_folder))?
+            {
+                rt.block_on(store.remap_moved_uids(source_id, folder_id, &uids))?;
+            }
+        }
+        MailEvent::DraftSaved {
+            message_id,
+            uid,
+            raw,
+            ..
+        } => {
+            rt.block_on(store.mark_draft_uploaded(message_id, uid, &raw))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
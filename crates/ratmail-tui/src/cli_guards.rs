@@ -65,7 +65,16 @@ fn all_message_fields() -> HashSet<String> {
         "cc",
         "subject",
         "unread",
+        "flagged",
+        "keywords",
+        "thread_id",
         "preview",
+        "message_id",
+        "in_reply_to",
+        "references",
+        "reply_to",
+        "list_id",
+        "size",
         "body",
         "raw",
         "attachments",
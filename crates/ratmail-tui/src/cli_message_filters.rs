@@ -54,6 +54,9 @@ pub(crate) fn spec_matches_text_fields_cli(
     to: Option<&str>,
     cc: Option<&str>,
 ) -> bool {
+    if !spec.gmail.is_empty() {
+        return false;
+    }
     let needle = spec.text.trim();
     if !needle.is_empty()
         && !summary.from.to_ascii_lowercase().contains(needle)
@@ -72,6 +75,11 @@ pub(crate) fn spec_matches_text_fields_cli(
             return false;
         }
     }
+    for body in &spec.body {
+        if !summary.preview.to_ascii_lowercase().contains(body) {
+            return false;
+        }
+    }
     if !spec.to.is_empty() {
         let to_raw = to.unwrap_or("");
         let cc_raw = cc.unwrap_or("");
@@ -157,7 +165,12 @@ pub(crate) fn maybe_fetch_raw(
     message_id: i64,
     fetch: bool,
 ) -> Result<Option<Vec<u8>>> {
-    if let Some(raw) = rt.block_on(store.get_raw_body(message_id))? {
+    let cached = rt.block_on(store.get_raw_body(message_id))?;
+    // A body without its attachments is refetched whole when fetching is allowed.
+    let complete = cached.is_some() && rt.block_on(store.get_missing_parts(message_id))?.is_empty();
+    if let Some(raw) = cached
+        && (complete || !fetch)
+    {
         return Ok(Some(raw));
     }
     if !fetch {
@@ -176,12 +189,13 @@ pub(crate) fn maybe_fetch_raw(
 
 #[cfg(test)]
 mod tests {
-    use ratmail_core::AttachmentMeta;
+    use ratmail_core::{AttachmentMeta, MessageSummary};
 
     use super::{
         from_matches_filter, parse_before_ts, parse_since_ts, spec_matches_attachments_cli,
+        spec_matches_text_fields_cli,
     };
-    use crate::SearchSpec;
+    use crate::{SearchSpec, parse_search_spec};
 
     #[test]
     fn parse_date_filters_accept_and_reject() {
@@ -230,4 +244,46 @@ mod tests {
         }];
         assert!(!spec_matches_attachments_cli(&spec, &attachments));
     }
+
+    #[test]
+    fn recipient_filter_matches_stored_to_and_cc() {
+        let spec = parse_search_spec("to:carol sort:size");
+        assert!(spec.sort_by_size);
+        assert!(!spec.needs_attachments());
+        let summary = MessageSummary {
+            id: 1,
+            folder_id: 1,
+            imap_uid: Some(7),
+            date: "2025-03-01 09:30".to_string(),
+            from: "Alice <alice@example.com>".to_string(),
+            subject: "Quarterly numbers".to_string(),
+            unread: false,
+            flagged: false,
+            keywords: Vec::new(),
+            gmail_msgid: None,
+            gmail_thread_id: None,
+            preview: "Numbers attached".to_string(),
+            to: "Bob <bob@example.com>".to_string(),
+            cc: "Carol <carol@example.com>".to_string(),
+            message_id_header: String::new(),
+            in_reply_to: String::new(),
+            references: String::new(),
+            reply_to: String::new(),
+            list_id: String::new(),
+            size: Some(48213),
+        };
+        assert!(spec_matches_text_fields_cli(
+            &spec,
+            &summary,
+            Some(&summary.to),
+            Some(&summary.cc)
+        ));
+        let spec = parse_search_spec("to:dave");
+        assert!(!spec_matches_text_fields_cli(
+            &spec,
+            &summary,
+            Some(&summary.to),
+            Some(&summary.cc)
+        ));
+    }
 }
@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use anyhow::Result;
 use clap::Parser;
 use ratmail_core::MessageSummary;
+use ratmail_mail::ImapErrorKind;
 use serde_json::{Value as JsonValue, json};
 
 use super::{AccountConfig, CLI_SCHEMA_VERSION, Cli, CliCommand, CliConfig, shell_split};
@@ -31,6 +32,21 @@ pub(crate) fn output_error(message: &str) -> Result<()> {
     Ok(())
 }
 
+/// Like `output_error`, plus `error_kind` so scripts can tell a rejected
+/// login from a network outage; `null` when no IMAP error was reported.
+pub(crate) fn output_imap_error(message: &str, kind: Option<ImapErrorKind>) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string(&json!({
+            "schema": CLI_SCHEMA_VERSION,
+            "ok": false,
+            "error": message,
+            "error_kind": kind,
+        }))?
+    );
+    Ok(())
+}
+
 pub(crate) fn resolve_cli_command(cli: Cli) -> Result<(bool, Option<CliCommand>)> {
     let cli_requested = cli.cmd.is_some() || cli.command.is_some();
     if let Some(cmd) = cli.cmd {
@@ -90,8 +106,35 @@ pub(crate) fn filter_summary_to_json(
     if allowed.contains("unread") {
         map.insert("unread".to_string(), json!(summary.unread));
     }
+    if allowed.contains("flagged") {
+        map.insert("flagged".to_string(), json!(summary.flagged));
+    }
+    if allowed.contains("keywords") {
+        map.insert("keywords".to_string(), json!(summary.keywords));
+    }
+    if allowed.contains("thread_id") {
+        map.insert("thread_id".to_string(), json!(summary.gmail_thread_id));
+    }
     if allowed.contains("preview") {
         map.insert("preview".to_string(), json!(summary.preview));
     }
+    if allowed.contains("message_id") {
+        map.insert("message_id".to_string(), json!(summary.message_id_header));
+    }
+    if allowed.contains("in_reply_to") {
+        map.insert("in_reply_to".to_string(), json!(summary.in_reply_to));
+    }
+    if allowed.contains("references") {
+        map.insert("references".to_string(), json!(summary.references));
+    }
+    if allowed.contains("reply_to") {
+        map.insert("reply_to".to_string(), json!(summary.reply_to));
+    }
+    if allowed.contains("list_id") {
+        map.insert("list_id".to_string(), json!(summary.list_id));
+    }
+    if allowed.contains("size") {
+        map.insert("size".to_string(), json!(summary.size));
+    }
     JsonValue::Object(map)
 }
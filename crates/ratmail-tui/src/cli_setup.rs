@@ -16,6 +16,10 @@ use ratatui::{
     text::{Line, Span, Text},
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
 };
+use ratmail_mail::{
+    KEYRING_SERVICE, MailProtocol, TlsSecurity, keyring_account, keyring_store, probe_certificate,
+    trust_certificate,
+};
 use serde_json::json;
 
 use super::{AccountConfig, output_ok};
@@ -55,11 +59,34 @@ pub(crate) struct OnboardingField {
     secret: bool,
 }
 
+/// Where the wizard puts a typed password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PasswordStorage {
+    Keyring,
+    ConfigFile,
+}
+
+impl PasswordStorage {
+    fn label(self) -> &'static str {
+        match self {
+            PasswordStorage::Keyring => "System keyring (Keychain / Secret Service)",
+            PasswordStorage::ConfigFile => "ratmail.toml (plain text)",
+        }
+    }
+
+    fn all() -> [PasswordStorage; 2] {
+        [PasswordStorage::Keyring, PasswordStorage::ConfigFile]
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct OnboardingAccountDraft {
     name: String,
     email: String,
     password: String,
+    /// Replaces `password` when set.
+    password_command: String,
+    password_storage: PasswordStorage,
     display_name: String,
     imap_host: String,
     imap_port: u16,
@@ -78,6 +105,8 @@ pub(crate) enum OnboardingStep {
     Welcome,
     Provider,
     AccountForm,
+    PasswordStorage,
+    TrustCertificate,
     AddAnotherAccount,
     Complete,
 }
@@ -133,6 +162,11 @@ pub(crate) fn build_onboarding_fields(provider: OnboardingProvider) -> Vec<Onboa
             value: String::new(),
             secret: true,
         },
+        OnboardingField {
+            label: "Or password command (e.g. pass show mail/work)",
+            value: String::new(),
+            secret: false,
+        },
         OnboardingField {
             label: "Display name (for sent From)",
             value: String::new(),
@@ -172,25 +206,26 @@ pub(crate) fn parse_onboarding_account(
     let name = get(0).to_string();
     let email = get(1).to_string();
     let password = get(2).to_string();
-    let display_name = get(3).to_string();
+    let password_command = get(3).to_string();
+    let display_name = get(4).to_string();
     if name.is_empty() {
         return Err(anyhow::anyhow!("Account name is required"));
     }
     if email.is_empty() {
         return Err(anyhow::anyhow!("Email / username is required"));
     }
-    if password.is_empty() {
-        return Err(anyhow::anyhow!("Password is required"));
+    if password.is_empty() && password_command.is_empty() {
+        return Err(anyhow::anyhow!("Password or password command is required"));
     }
     let (mut imap_host, mut imap_port, mut smtp_host, mut smtp_port, skip_tls_verify) =
         provider_presets(provider);
     if provider == OnboardingProvider::OtherImap {
-        imap_host = get(4).to_string();
-        imap_port = get(5)
+        imap_host = get(5).to_string();
+        imap_port = get(6)
             .parse::<u16>()
             .map_err(|_| anyhow::anyhow!("IMAP port must be a number"))?;
-        smtp_host = get(6).to_string();
-        smtp_port = get(7)
+        smtp_host = get(7).to_string();
+        smtp_port = get(8)
             .parse::<u16>()
             .map_err(|_| anyhow::anyhow!("SMTP port must be a number"))?;
     }
@@ -201,6 +236,8 @@ pub(crate) fn parse_onboarding_account(
         name,
         email,
         password,
+        password_command,
+        password_storage: PasswordStorage::ConfigFile,
         display_name,
         imap_host,
         imap_port,
@@ -210,6 +247,62 @@ pub(crate) fn parse_onboarding_account(
     })
 }
 
+/// Saves the typed password for both servers in the system keyring.
+fn store_password_in_keyring(account: &OnboardingAccountDraft) -> Result<()> {
+    for host in [&account.imap_host, &account.smtp_host] {
+        keyring_store(
+            KEYRING_SERVICE,
+            &keyring_account(host, &account.email),
+            &account.password,
+        )?;
+    }
+    Ok(())
+}
+
+/// A server certificate that failed validation during setup.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingCertificate {
+    host: String,
+    port: u16,
+    fingerprint: String,
+}
+
+/// Probes both servers of a new account; unreachable servers are skipped and
+/// will be asked about again on first connect.
+fn untrusted_certificates(account: &OnboardingAccountDraft) -> Vec<PendingCertificate> {
+    [
+        (MailProtocol::Imap, &account.imap_host, account.imap_port),
+        (MailProtocol::Smtp, &account.smtp_host, account.smtp_port),
+    ]
+    .into_iter()
+    .filter(|_| !account.skip_tls_verify)
+    .filter_map(|(protocol, host, port)| {
+        let security = TlsSecurity::default_for_port(port);
+        let fingerprint = probe_certificate(protocol, host, port, security, None, None).ok()??;
+        Some(PendingCertificate {
+            host: host.clone(),
+            port,
+            fingerprint,
+        })
+    })
+    .collect()
+}
+
+/// Stores a completed account, then asks about its certificates if needed.
+fn finish_onboarding_account(
+    account: OnboardingAccountDraft,
+    accounts: &mut Vec<OnboardingAccountDraft>,
+    certificates: &mut Vec<PendingCertificate>,
+) -> OnboardingStep {
+    certificates.extend(untrusted_certificates(&account));
+    accounts.push(account);
+    if certificates.is_empty() {
+        OnboardingStep::AddAnotherAccount
+    } else {
+        OnboardingStep::TrustCertificate
+    }
+}
+
 pub(crate) fn render_onboarding_ui(
     frame: &mut ratatui::Frame,
     step: OnboardingStep,
@@ -217,6 +310,8 @@ pub(crate) fn render_onboarding_ui(
     provider_idx: usize,
     fields: &[OnboardingField],
     field_idx: usize,
+    storage_idx: usize,
+    certificate: Option<&PendingCertificate>,
     add_another_account: bool,
     error: Option<&str>,
 ) {
@@ -272,7 +367,7 @@ pub(crate) fn render_onboarding_ui(
                 .filter(|v| !v.is_empty())
                 .unwrap_or("you@example.com");
             let typed_name = fields
-                .get(3)
+                .get(4)
                 .map(|f| f.value.trim())
                 .filter(|v| !v.is_empty())
                 .unwrap_or("Name");
@@ -284,6 +379,35 @@ pub(crate) fn render_onboarding_ui(
                 "Type to edit   Up/Down/Tab: move   Enter: next/save",
             ));
         }
+        OnboardingStep::PasswordStorage => {
+            lines.push(Line::from("Where should the password be stored?"));
+            lines.push(Line::from(""));
+            for (idx, storage) in PasswordStorage::all().iter().enumerate() {
+                let prefix = if idx == storage_idx { ">" } else { " " };
+                lines.push(Line::from(format!("{} {}", prefix, storage.label())));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(
+                "Up/Down: select   Enter: continue   Esc: cancel",
+            ));
+        }
+        OnboardingStep::TrustCertificate => {
+            if let Some(certificate) = certificate {
+                lines.push(Line::from(format!(
+                    "The certificate of {}:{} could not be verified.",
+                    certificate.host, certificate.port
+                )));
+                lines.push(Line::from(
+                    "It may be self-signed. Compare its fingerprint with the one your server shows:",
+                ));
+                lines.push(Line::from(""));
+                lines.push(Line::from(format!("SHA-256 {}", certificate.fingerprint)));
+                lines.push(Line::from(""));
+                lines.push(Line::from(
+                    "y: trust this certificate   n: skip   Esc: cancel",
+                ));
+            }
+        }
         OnboardingStep::AddAnotherAccount => {
             lines.push(Line::from("Add another email account?"));
             lines.push(Line::from(""));
@@ -343,7 +467,10 @@ pub(crate) fn run_onboarding_splash() -> Result<Option<OnboardingResult>> {
     let mut provider_idx = 0usize;
     let mut fields = Vec::<OnboardingField>::new();
     let mut field_idx = 0usize;
+    let mut storage_idx = 0usize;
     let mut add_another_account = false;
+    let mut pending_account: Option<OnboardingAccountDraft> = None;
+    let mut certificates: Vec<PendingCertificate> = Vec::new();
     let mut accounts: Vec<OnboardingAccountDraft> = Vec::new();
     let mut error: Option<String> = None;
 
@@ -357,6 +484,8 @@ pub(crate) fn run_onboarding_splash() -> Result<Option<OnboardingResult>> {
                 provider_idx,
                 &fields,
                 field_idx,
+                storage_idx,
+                certificates.first(),
                 add_another_account,
                 error.as_deref(),
             )
@@ -410,10 +539,18 @@ pub(crate) fn run_onboarding_splash() -> Result<Option<OnboardingResult>> {
                     } else {
                         let provider = OnboardingProvider::all()[provider_idx];
                         match parse_onboarding_account(provider, &fields) {
+                            Ok(parsed) if parsed.password_command.is_empty() => {
+                                pending_account = Some(parsed);
+                                storage_idx = 0;
+                                step = OnboardingStep::PasswordStorage;
+                            }
                             Ok(parsed) => {
-                                accounts.push(parsed);
                                 add_another_account = false;
-                                step = OnboardingStep::AddAnotherAccount;
+                                step = finish_onboarding_account(
+                                    parsed,
+                                    &mut accounts,
+                                    &mut certificates,
+                                );
                             }
                             Err(err) => error = Some(err.to_string()),
                         }
@@ -426,6 +563,64 @@ pub(crate) fn run_onboarding_splash() -> Result<Option<OnboardingResult>> {
                 }
                 _ => {}
             },
+            OnboardingStep::PasswordStorage => match key.code {
+                KeyCode::Esc => break Ok(None),
+                KeyCode::Up => storage_idx = storage_idx.saturating_sub(1),
+                KeyCode::Down => {
+                    storage_idx = (storage_idx + 1).min(PasswordStorage::all().len() - 1);
+                }
+                KeyCode::Enter => {
+                    let storage = PasswordStorage::all()[storage_idx];
+                    if let Some(mut account) = pending_account.take() {
+                        let stored = match storage {
+                            PasswordStorage::Keyring => store_password_in_keyring(&account),
+                            PasswordStorage::ConfigFile => Ok(()),
+                        };
+                        match stored {
+                            Ok(()) => {
+                                account.password_storage = storage;
+                                add_another_account = false;
+                                step = finish_onboarding_account(
+                                    account,
+                                    &mut accounts,
+                                    &mut certificates,
+                                );
+                            }
+                            Err(err) => {
+                                error = Some(err.to_string());
+                                pending_account = Some(account);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            },
+            OnboardingStep::TrustCertificate => match key.code {
+                KeyCode::Esc => break Ok(None),
+                KeyCode::Char('y')
+                | KeyCode::Char('Y')
+                | KeyCode::Char('n')
+                | KeyCode::Char('N') => {
+                    let trust = matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y'));
+                    if !certificates.is_empty() {
+                        let certificate = certificates.remove(0);
+                        if trust
+                            && let Err(err) = trust_certificate(
+                                &certificate.host,
+                                certificate.port,
+                                &certificate.fingerprint,
+                            )
+                        {
+                            error = Some(err.to_string());
+                            certificates.insert(0, certificate);
+                        }
+                    }
+                    if certificates.is_empty() {
+                        step = OnboardingStep::AddAnotherAccount;
+                    }
+                }
+                _ => {}
+            },
             OnboardingStep::AddAnotherAccount => match key.code {
                 KeyCode::Esc => break Ok(None),
                 KeyCode::Char(' ') => add_another_account = !add_another_account,
@@ -506,16 +701,18 @@ pub(crate) fn upsert_account_in_config(account: &OnboardingAccountDraft) -> Resu
         "username".to_string(),
         toml::Value::String(account.email.clone()),
     );
-    imap.insert(
-        "password".to_string(),
-        toml::Value::String(account.password.clone()),
-    );
+    insert_password(&mut imap, account);
     imap.insert(
         "skip_tls_verify".to_string(),
         toml::Value::Boolean(account.skip_tls_verify),
     );
     imap.insert("initial_sync_days".to_string(), toml::Value::Integer(90));
     imap.insert("fetch_chunk_size".to_string(), toml::Value::Integer(10));
+    imap.insert(
+        "idle_folders".to_string(),
+        toml::Value::Array(vec![toml::Value::String("INBOX".to_string())]),
+    );
+    imap.insert("idle_poll_secs".to_string(), toml::Value::Integer(120));
 
     let from_value = if account.display_name.trim().is_empty() {
         account.email.clone()
@@ -535,15 +732,17 @@ pub(crate) fn upsert_account_in_config(account: &OnboardingAccountDraft) -> Resu
         "username".to_string(),
         toml::Value::String(account.email.clone()),
     );
-    smtp.insert(
-        "password".to_string(),
-        toml::Value::String(account.password.clone()),
-    );
+    insert_password(&mut smtp, account);
     smtp.insert("from".to_string(), toml::Value::String(from_value));
     smtp.insert(
         "skip_tls_verify".to_string(),
         toml::Value::Boolean(account.skip_tls_verify),
     );
+    // Gmail files submitted mail into Sent on its own.
+    smtp.insert(
+        "save_sent".to_string(),
+        toml::Value::Boolean(!account.smtp_host.eq_ignore_ascii_case("smtp.gmail.com")),
+    );
 
     account_table.insert("imap".to_string(), toml::Value::Table(imap));
     account_table.insert("smtp".to_string(), toml::Value::Table(smtp));
@@ -553,6 +752,30 @@ pub(crate) fn upsert_account_in_config(account: &OnboardingAccountDraft) -> Resu
     write_text_atomic(&cfg_path, &serialized)
 }
 
+fn insert_password(
+    table: &mut toml::map::Map<String, toml::Value>,
+    account: &OnboardingAccountDraft,
+) {
+    if !account.password_command.is_empty() {
+        table.insert(
+            "password_command".to_string(),
+            toml::Value::String(account.password_command.clone()),
+        );
+        return;
+    }
+    match account.password_storage {
+        PasswordStorage::Keyring => {
+            table.insert("password_keyring".to_string(), toml::Value::Boolean(true));
+        }
+        PasswordStorage::ConfigFile => {
+            table.insert(
+                "password".to_string(),
+                toml::Value::String(account.password.clone()),
+            );
+        }
+    }
+}
+
 pub(crate) fn run_setup_wizard(accounts: &[AccountConfig], emit_json: bool) -> Result<()> {
     let onboarding = run_onboarding_splash()?;
     let Some(onboarding) = onboarding else {
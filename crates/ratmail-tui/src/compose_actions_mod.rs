@@ -1,5 +1,6 @@
 use ratmail_content::{extract_attachment_data, extract_display};
 use ratmail_core::{DEFAULT_TEXT_WIDTH, MailStore};
+use ratmail_mail::insert_body_part;
 
 use super::{
     App, ComposeFocus, ComposeStartAction, ComposeVimMode, Mode, StoreUpdate, build_forward,
@@ -22,6 +23,8 @@ impl App {
         self.compose_body = compose_buffer_from_body(self.ui_theme.clone(), "");
         self.compose_quote.clear();
         self.compose_attachments.clear();
+        self.compose_pending_parts.clear();
+        self.compose_draft_id = None;
         self.compose_focus = ComposeFocus::To;
         self.compose_cursor_to = 0;
         self.compose_cursor_from = text_char_len(&self.compose_from);
@@ -117,6 +120,8 @@ impl App {
         self.compose_body = compose_buffer_from_body(self.ui_theme.clone(), &quote);
         self.compose_quote.clear();
         self.compose_attachments.clear();
+        self.compose_pending_parts.clear();
+        self.compose_draft_id = None;
         if include_attachments {
             self.include_selected_attachments_in_compose();
         }
@@ -150,6 +155,8 @@ impl App {
         self.compose_body = compose_buffer_from_body(self.ui_theme.clone(), &body);
         self.compose_quote.clear();
         self.compose_attachments.clear();
+        self.compose_pending_parts.clear();
+        self.compose_draft_id = None;
         if include_attachments {
             self.include_selected_attachments_in_compose();
         }
@@ -181,8 +188,16 @@ impl App {
             }
         };
         let mut added = 0usize;
+        let mut downloading = 0usize;
         for index in 0..expected {
             if let Ok(Some(data)) = extract_attachment_data(&raw, index) {
+                if let Some(section) = data.omitted_section {
+                    self.fetch_body_part(message_id, section.clone());
+                    self.compose_pending_parts
+                        .push((message_id, section, index));
+                    downloading += 1;
+                    continue;
+                }
                 let size = data.data.len();
                 self.compose_attachments.push(super::ComposeAttachment {
                     filename: data.filename,
@@ -193,17 +208,58 @@ impl App {
                 added += 1;
             }
         }
-        if added == 0 {
+        if downloading > 0 {
+            self.set_status(format!(
+                "Included {} of {} attachments; {} still downloading",
+                added, expected, downloading
+            ));
+        } else if added == 0 {
             self.set_status("Unable to include attachments");
         } else if added < expected {
             self.set_status(format!("Included {} of {} attachments", added, expected));
         }
     }
 
+    /// Adds a forwarded attachment to compose once its part has downloaded.
+    pub(crate) fn attach_downloaded_part(&mut self, message_id: i64, section: &str, data: &[u8]) {
+        let Some(pos) = self
+            .compose_pending_parts
+            .iter()
+            .position(|(id, pending, _)| *id == message_id && pending == section)
+        else {
+            return;
+        };
+        let (_, _, index) = self.compose_pending_parts.remove(pos);
+        let raw = self.runtime().block_on(async {
+            self.store_handle
+                .get_raw_body(message_id)
+                .await
+                .ok()
+                .flatten()
+        });
+        // The store may already hold the part when this runs.
+        let attachment = raw
+            .map(|raw| insert_body_part(&raw, section, data).unwrap_or(raw))
+            .and_then(|raw| extract_attachment_data(&raw, index).ok().flatten())
+            .filter(|data| data.omitted_section.is_none());
+        let Some(attachment) = attachment else {
+            self.set_status("Unable to include attachment");
+            return;
+        };
+        self.set_status(format!("Attached {}", attachment.filename));
+        self.compose_attachments.push(super::ComposeAttachment {
+            filename: attachment.filename,
+            mime: attachment.mime,
+            size: attachment.data.len(),
+            data: attachment.data,
+        });
+    }
+
     pub(crate) fn start_compose_draft(&mut self) {
         let Some(summary) = self.selected_message() else {
             return;
         };
+        let draft_id = summary.id;
         let raw = self.runtime().block_on(async {
             self.store_handle
                 .get_raw_body(summary.id)
@@ -243,6 +299,8 @@ impl App {
         self.compose_body = compose_buffer_from_body(self.ui_theme.clone(), &body);
         self.compose_quote.clear();
         self.compose_attachments.clear();
+        self.compose_pending_parts.clear();
+        self.compose_draft_id = Some(draft_id);
         self.compose_cursor_from = text_char_len(&self.compose_from);
         self.compose_cursor_to = text_char_len(&self.compose_to);
         self.compose_cursor_cc = text_char_len(&self.compose_cc);
@@ -317,6 +375,8 @@ impl App {
             bcc: self.compose_bcc.clone(),
             subject: self.compose_subject.clone(),
             body,
+            replaces: self.compose_draft_id,
+            send_op: None,
         });
         if self.compose_attachments.is_empty() {
             self.set_status("Draft saved");
@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratmail_core::FolderRole;
 use ratmail_mail::{MailCommand, OutgoingAttachment};
 
 use super::{
@@ -59,9 +60,18 @@ impl App {
                 self.set_status("No recipient");
             } else if from.is_empty() {
                 self.set_status("No sender");
+            } else if !self.compose_pending_parts.is_empty() {
+                // Sending again goes ahead without them.
+                let pending = self.compose_pending_parts.len();
+                self.set_status(format!(
+                    "{} attachment{} not downloaded yet; send again to leave {} out",
+                    pending,
+                    if pending == 1 { "" } else { "s" },
+                    if pending == 1 { "it" } else { "them" },
+                ));
+                self.compose_pending_parts.clear();
             } else {
-                self.set_status("Sending...");
-                let _ = self.engine.send(MailCommand::SendMessage {
+                let command = MailCommand::SendMessage {
                     from: Some(from),
                     to,
                     cc,
@@ -70,7 +80,18 @@ impl App {
                     body,
                     body_html,
                     attachments,
-                });
+                    sent_folder: self.folder_name_by_role(FolderRole::Sent),
+                };
+                // Compose is only left once the queued op finishes, so the
+                // message must be stored before it is sent.
+                match self.queue_command(command) {
+                    Ok(op_id) => {
+                        self.set_status("Sending...");
+                        self.sending_draft_id = self.compose_draft_id;
+                        self.sending_op = Some(op_id);
+                    }
+                    Err(err) => self.set_status(format!("Send failed: {}", err)),
+                }
             }
             return false;
         }
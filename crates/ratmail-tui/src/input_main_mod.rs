@@ -17,10 +17,12 @@ impl App {
             | Mode::OverlayAttach
             | Mode::OverlayBulkAction
             | Mode::OverlayBulkMove
+            | Mode::OverlayTags
             | Mode::OverlayConfirmDelete
             | Mode::OverlayConfirmLink
             | Mode::OverlayConfirmDraft
-            | Mode::OverlayConfirmComposeAttachments => self.on_key_overlay(key),
+            | Mode::OverlayConfirmComposeAttachments
+            | Mode::OverlayTrustCertificate => self.on_key_overlay(key),
         }
     }
 
@@ -215,6 +217,14 @@ impl App {
                     self.open_confirm_delete(ids, self.mode);
                 }
             }
+            (KeyCode::Char('F'), _) if self.focus == Focus::Messages => {
+                let ids = self.active_message_ids();
+                self.queue_toggle_flagged_messages(ids);
+            }
+            (KeyCode::Char('t'), _) if self.focus == Focus::Messages => {
+                let ids = self.active_message_ids();
+                self.open_tags_overlay(ids, self.mode);
+            }
             (KeyCode::Char('y'), _) => {
                 if self.focus == Focus::Messages {
                     self.copy_auth_code_for_selected();
@@ -175,6 +175,7 @@ impl App {
                 }
                 KeyCode::Enter => {
                     self.mode = self.overlay_return;
+                    self.request_server_search();
                 }
                 KeyCode::Left => move_cursor_left(&self.search_query, &mut self.search_cursor),
                 KeyCode::Right => move_cursor_right(&self.search_query, &mut self.search_cursor),
@@ -374,10 +375,19 @@ impl App {
                     self.queue_mark_read_messages(ids);
                     self.mode = self.bulk_done_return;
                 }
+                KeyCode::Char('F') => {
+                    let ids = self.bulk_action_ids.clone();
+                    self.queue_toggle_flagged_messages(ids);
+                    self.mode = self.bulk_done_return;
+                }
                 KeyCode::Char('m') => {
                     let ids = self.bulk_action_ids.clone();
                     self.open_move_overlay(ids, Mode::OverlayBulkAction);
                 }
+                KeyCode::Char('t') => {
+                    let ids = self.bulk_action_ids.clone();
+                    self.open_tags_overlay(ids, Mode::OverlayBulkAction);
+                }
                 KeyCode::Char('d') => {
                     let ids = self.bulk_action_ids.clone();
                     self.open_confirm_delete(ids, Mode::OverlayBulkAction);
@@ -412,6 +422,34 @@ impl App {
                 }
                 _ => {}
             },
+            Mode::OverlayTags => match key.code {
+                KeyCode::Esc => {
+                    if self.overlay_return == Mode::OverlayBulkAction {
+                        self.mode = self.bulk_done_return;
+                    } else {
+                        self.mode = self.overlay_return;
+                    }
+                }
+                KeyCode::Down if self.tag_index + 1 < self.tag_choices().len() => {
+                    self.tag_index += 1;
+                }
+                KeyCode::Up => {
+                    self.tag_index = self.tag_index.saturating_sub(1);
+                }
+                KeyCode::Enter if self.tag_input.trim().is_empty() => {
+                    self.toggle_selected_tag();
+                }
+                KeyCode::Enter => {
+                    self.submit_tag_input();
+                }
+                KeyCode::Backspace => {
+                    self.tag_input.pop();
+                }
+                KeyCode::Char(ch) => {
+                    self.tag_input.push(ch);
+                }
+                _ => {}
+            },
             Mode::OverlayConfirmDelete => match key.code {
                 KeyCode::Esc => {
                     self.mode = self.overlay_return;
@@ -427,6 +465,19 @@ impl App {
                 }
                 _ => {}
             },
+            Mode::OverlayTrustCertificate => match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => self.trust_confirmed_certificate(),
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    if let Some(certificate) = self.confirm_certificate.take() {
+                        self.set_status(format!(
+                            "Certificate of {}:{} not trusted",
+                            certificate.host, certificate.port
+                        ));
+                    }
+                    self.mode = self.confirm_certificate_return;
+                }
+                _ => {}
+            },
             Mode::OverlayConfirmDraft => match key.code {
                 KeyCode::Esc => {
                     self.mode = Mode::Compose;
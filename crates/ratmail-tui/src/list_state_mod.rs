@@ -1,12 +1,12 @@
 use std::collections::HashSet;
 
 use ratmail_content::extract_attachments;
-use ratmail_core::{AttachmentMeta, Folder, MailStore, MessageDetail, MessageSummary};
+use ratmail_core::{AttachmentMeta, Folder, FolderRole, MailStore, MessageDetail, MessageSummary};
 use ratmail_mail::MailCommand;
 
 use super::{
-    App, Mode, SearchSpec, ViewMode, canonical_folder_name, clamp_cursor, from_matches_filter,
-    parse_search_spec, text_char_len,
+    App, Mode, SearchSpec, ViewMode, clamp_cursor, from_matches_filter, parse_search_spec,
+    text_char_len,
 };
 
 impl App {
@@ -57,6 +57,9 @@ impl App {
         message: &MessageSummary,
         detail: Option<&MessageDetail>,
     ) -> bool {
+        if !self.search_spec.gmail.is_empty() {
+            return false;
+        }
         let needle = self.search_spec.text.trim();
         if !needle.is_empty()
             && !message.from.to_ascii_lowercase().contains(needle)
@@ -75,14 +78,25 @@ impl App {
                 return false;
             }
         }
-        if !self.search_spec.to.is_empty() {
-            let Some(detail) = detail else {
+        for body in &self.search_spec.body {
+            let in_body = detail.is_some_and(|d| d.body.to_ascii_lowercase().contains(body));
+            if !in_body && !message.preview.to_ascii_lowercase().contains(body) {
                 return false;
+            }
+        }
+        if !self.search_spec.to.is_empty() {
+            // Rows synced before recipients were stored only have them in the body.
+            let (to, cc) = if message.to.is_empty() && message.cc.is_empty() {
+                let Some(detail) = detail else {
+                    return false;
+                };
+                (detail.to.as_str(), detail.cc.as_str())
+            } else {
+                (message.to.as_str(), message.cc.as_str())
             };
-            for to in &self.search_spec.to {
-                let needle = to.as_str();
-                if !detail.to.to_ascii_lowercase().contains(needle)
-                    && !detail.cc.to_ascii_lowercase().contains(needle)
+            for needle in &self.search_spec.to {
+                if !to.to_ascii_lowercase().contains(needle)
+                    && !cc.to_ascii_lowercase().contains(needle)
                 {
                     return false;
                 }
@@ -140,12 +154,29 @@ impl App {
         self.store.folders.get(self.folder_index)
     }
 
+    pub(crate) fn folder_name_by_role(&self, role: FolderRole) -> Option<String> {
+        self.store
+            .folders
+            .iter()
+            .find(|f| f.role == Some(role))
+            .map(|f| f.name.clone())
+    }
+
     pub(crate) fn selected_folder_is_drafts(&self) -> bool {
         self.selected_folder()
-            .map(|f| canonical_folder_name(&f.name) == "Drafts")
+            .map(|f| f.role == Some(FolderRole::Drafts))
             .unwrap_or(false)
     }
 
+    /// Deleting outside Trash moves messages there unless the account opts
+    /// into permanent deletes.
+    pub(crate) fn delete_moves_to_trash(&self) -> bool {
+        !self.permanent_delete
+            && self
+                .selected_folder()
+                .is_some_and(|f| f.role != Some(FolderRole::Trash))
+    }
+
     pub(crate) fn select_inbox_if_available(&mut self) {
         if let Some((idx, _)) = self
             .store
@@ -168,14 +199,16 @@ impl App {
 
     pub(crate) fn visible_messages(&self) -> Vec<&MessageSummary> {
         let folder_id = self.selected_folder().map(|f| f.id);
-        let messages: Vec<&MessageSummary> = self
+        let mut messages: Vec<&MessageSummary> = self
             .store
             .messages
             .iter()
             .filter(|msg| Some(msg.folder_id) == folder_id)
             .filter(|msg| {
                 let detail = self.store.message_details.get(&msg.id);
-                if !self.search_spec_matches_text_fields(msg, detail) {
+                if !self.is_server_search_hit(msg)
+                    && !self.search_spec_matches_text_fields(msg, detail)
+                {
                     return false;
                 }
                 match self.attachments_match_search(msg.id) {
@@ -186,9 +219,21 @@ impl App {
             })
             .collect();
         // Preserve backend ordering (already sorted by date).
+        if self.search_spec.sort_by_size {
+            messages.sort_by_key(|msg| std::cmp::Reverse(msg.size));
+        }
         messages
     }
 
+    fn is_server_search_hit(&self, message: &MessageSummary) -> bool {
+        self.server_search.as_ref().is_some_and(|search| {
+            search.folder_id == message.folder_id
+                && message
+                    .imap_uid
+                    .is_some_and(|uid| search.uids.contains(&uid))
+        })
+    }
+
     pub(crate) fn prune_selected_messages(&mut self) {
         if self.selected_message_ids.is_empty() {
             return;
@@ -225,7 +270,8 @@ impl App {
     pub(crate) fn on_search_updated(&mut self) {
         self.search_cursor = clamp_cursor(self.search_cursor, &self.search_query);
         self.search_spec = Self::parse_search_spec(&self.search_query);
-        if self.search_spec.needs_raw() {
+        self.server_search = None;
+        if self.search_spec.needs_attachments() {
             self.refresh_search_attachment_queue();
             self.prefetch_search_attachments_step(8);
         } else {
@@ -241,7 +287,7 @@ impl App {
     }
 
     pub(crate) fn refresh_search_attachment_queue(&mut self) {
-        if !self.search_spec.needs_raw() {
+        if !self.search_spec.needs_attachments() {
             self.search_attachment_queue.clear();
             return;
         }
@@ -316,7 +362,7 @@ impl App {
     }
 
     pub(crate) fn prefetch_search_attachments_step(&mut self, limit: usize) {
-        if !self.search_spec.needs_raw() || self.search_attachment_queue.is_empty() {
+        if !self.search_spec.needs_attachments() || self.search_attachment_queue.is_empty() {
             return;
         }
         let folder_name = self.selected_folder().map(|f| f.name.clone());
@@ -21,7 +21,7 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, Paragraph, Row, Table, TableState, Wrap},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
 };
 
 use image::{DynamicImage, GenericImageView};
@@ -32,10 +32,15 @@ use ratatui_image::{
 };
 use ratmail_content::extract_display;
 use ratmail_core::{
-    AttachmentMeta, DEFAULT_TEXT_WIDTH, Folder, FolderSyncState, LinkInfo, MailStore,
+    AttachmentMeta, DEFAULT_TEXT_WIDTH, Folder, FolderRole, FolderSyncState, LinkInfo, MailStore,
     MessageDetail, MessageSummary, SqliteMailStore, StoreSnapshot, TileMeta, log_debug,
 };
-use ratmail_mail::{ImapConfig, MailEngine, MailEvent, SmtpConfig};
+use ratmail_mail::{
+    ImapConfig, ImapFolderChanges, ImapMailboxState, ImapMessageSummary, ImapSearchQuery,
+    KEYRING_SERVICE, MailCommand, MailEngine, MailEvent, OAuth2Config, OAuth2Mechanism,
+    OutgoingDraft, PasswordSource, SmtpConfig, TlsSecurity, UntrustedCertificate, insert_body_part,
+    keyring_account,
+};
 use ratmail_render::{ChromiumRenderer, NullRenderer, Renderer, detect_image_support};
 use shell_words::split as shell_split;
 use spellbook::Dictionary;
@@ -55,10 +60,12 @@ mod message_actions_mod;
 mod message_parse_mod;
 mod multi_app_mod;
 mod overlay_mod;
+mod pending_ops_mod;
 mod picker_actions_mod;
 mod render_mod;
 mod render_state_mod;
 mod sync_mod;
+mod tags_mod;
 mod ui_theme_mod;
 mod util_mod;
 
@@ -77,9 +84,15 @@ use crate::overlay_mod::{
     render_confirm_compose_attachments_overlay, render_confirm_delete_overlay,
     render_confirm_draft_overlay, render_confirm_link_overlay, render_help_overlay,
     render_image_resize_overlay, render_links_overlay, render_picker_overlay,
-    render_search_overlay, render_spellcheck_overlay, render_toast,
+    render_search_overlay, render_spellcheck_overlay, render_tags_overlay, render_toast,
+    render_trust_certificate_overlay,
+};
+use crate::pending_ops_mod::{
+    PendingOps, moved_out_of, pending_op_label, pending_op_parks, queue_mail_command,
 };
 use crate::render_mod::{RenderEvent, RenderRequest, render_worker};
+use crate::sync_mod::SyncPoll;
+use crate::tags_mod::{keyword_color, keyword_hidden, keyword_label};
 use crate::util_mod::{
     format_size, picker_meta_lines, render_pdf_first_page, safe_filename, text_preview_from_bytes,
     zip_directory,
@@ -256,14 +269,25 @@ enum StoreUpdate {
         account_id: i64,
         folder_name: String,
         messages: Vec<MessageSummary>,
-        sync_update: Option<SyncUpdate>,
+        sync_update: SyncUpdate,
+        // UIDVALIDITY changed: drop everything cached for the folder first.
+        invalidate: bool,
+        changes: Option<ImapFolderChanges>,
     },
     RawBody {
         account_id: i64,
         message_id: i64,
         raw: Vec<u8>,
+        missing_parts: Vec<String>,
         cached_text: Option<String>,
     },
+    /// Splice a downloaded attachment into the cached body.
+    BodyPart {
+        account_id: i64,
+        message_id: i64,
+        section: String,
+        data: Vec<u8>,
+    },
     MoveMessages {
         account_id: i64,
         message_ids: Vec<i64>,
@@ -273,6 +297,21 @@ enum StoreUpdate {
     DeleteMessages {
         account_id: i64,
         message_ids: Vec<i64>,
+        to_trash: bool,
+        refresh_folder_id: i64,
+    },
+    RemapMovedUids {
+        account_id: i64,
+        source_folder: String,
+        folder_name: String,
+        uids: Vec<(u32, Option<u32>)>,
+        refresh_folder_id: i64,
+    },
+    /// A refused or dropped move: its messages go back to `source_folder`.
+    RestoreMovedMessages {
+        account_id: i64,
+        source_folder: String,
+        uids: Vec<u32>,
         refresh_folder_id: i64,
     },
     SetMessagesUnread {
@@ -281,6 +320,27 @@ enum StoreUpdate {
         unread: bool,
         refresh_folder_id: i64,
     },
+    SetMessagesFlagged {
+        account_id: i64,
+        message_ids: Vec<i64>,
+        flagged: bool,
+        refresh_folder_id: i64,
+    },
+    /// Headers found by a server-side search; stored without touching the
+    /// folder's sync state.
+    SearchResults {
+        account_id: i64,
+        folder_id: i64,
+        messages: Vec<MessageSummary>,
+        refresh_folder_id: i64,
+    },
+    UpdateMessageKeywords {
+        account_id: i64,
+        message_ids: Vec<i64>,
+        add: Vec<String>,
+        remove: Vec<String>,
+        refresh_folder_id: i64,
+    },
     SaveDraft {
         account_id: i64,
         from_addr: String,
@@ -289,11 +349,33 @@ enum StoreUpdate {
         bcc: String,
         subject: String,
         body: String,
+        /// Local id of the draft this save supersedes, if it was reopened.
+        replaces: Option<i64>,
+        /// Queued send whose message this draft keeps until it goes out.
+        send_op: Option<i64>,
+    },
+    /// The engine uploaded local draft `message_id` to the server.
+    DraftUploaded {
+        account_id: i64,
+        message_id: i64,
+        uid: Option<u32>,
+        raw: Vec<u8>,
+    },
+    /// A draft was sent; drop it locally and on the server.
+    DiscardDraft { account_id: i64, message_id: i64 },
+    SaveSent {
+        account_id: i64,
+        folder_name: String,
+        uid: Option<u32>,
+        raw: Vec<u8>,
     },
 }
 
 #[derive(Debug, Clone)]
 struct SyncUpdate {
+    uidvalidity: Option<i64>,
+    uidnext: Option<i64>,
+    highest_modseq: Option<i64>,
     last_seen_uid: Option<i64>,
     oldest_ts: Option<i64>,
     last_sync_ts: i64,
@@ -311,10 +393,12 @@ enum Mode {
     OverlayAttach,
     OverlayBulkAction,
     OverlayBulkMove,
+    OverlayTags,
     OverlayConfirmDelete,
     OverlayConfirmLink,
     OverlayConfirmDraft,
     OverlayConfirmComposeAttachments,
+    OverlayTrustCertificate,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -454,9 +538,15 @@ struct SearchSpec {
     from: Vec<String>,
     subject: Vec<String>,
     to: Vec<String>,
+    body: Vec<String>,
+    /// Raw Gmail search terms (`gm:has:attachment`); only the server can
+    /// evaluate them.
+    gmail: Vec<String>,
     date: Vec<String>,
     since_ts: Option<i64>,
     before_ts: Option<i64>,
+    /// `sort:size` lists the largest messages first.
+    sort_by_size: bool,
 }
 
 impl SearchSpec {
@@ -464,11 +554,45 @@ impl SearchSpec {
         !self.attachment_name.is_empty() || !self.attachment_type.is_empty()
     }
 
-    fn needs_raw(&self) -> bool {
-        self.needs_attachments() || !self.to.is_empty()
+    /// The part of the spec the server can evaluate with UID SEARCH, if any.
+    /// `date:` substrings and attachment filters stay local.
+    fn imap_query(&self) -> Option<ImapSearchQuery> {
+        let query = ImapSearchQuery {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            subject: self.subject.clone(),
+            body: self.body.clone(),
+            text: if self.text.is_empty() {
+                Vec::new()
+            } else {
+                vec![self.text.clone()]
+            },
+            gmail_raw: if self.gmail.is_empty() {
+                Vec::new()
+            } else {
+                vec![self.gmail.join(" ")]
+            },
+            since_ts: self.since_ts,
+            before_ts: self.before_ts,
+        };
+        if query.is_empty() { None } else { Some(query) }
     }
 }
 
+/// Newest matches fetched per server-side search.
+const SERVER_SEARCH_LIMIT: usize = 200;
+
+/// Results of the last server-side search; their UIDs count as matches even
+/// when the cached summary alone would not (e.g. `body:` terms).
+#[derive(Debug, Clone)]
+struct ServerSearch {
+    folder_id: i64,
+    folder_name: String,
+    query: ImapSearchQuery,
+    uids: HashSet<u32>,
+    pending: bool,
+}
+
 fn parse_search_spec(raw: &str) -> SearchSpec {
     let mut spec = SearchSpec::default();
     let mut text_parts = Vec::new();
@@ -496,7 +620,10 @@ fn parse_search_spec(raw: &str) -> SearchSpec {
             "from" => spec.from.push(value.to_string()),
             "subject" => spec.subject.push(value.to_string()),
             "to" => spec.to.push(value.to_string()),
+            "body" => spec.body.push(value.to_string()),
+            "gm" | "gmail" => spec.gmail.push(value.to_string()),
             "date" => spec.date.push(value.to_string()),
+            "sort" if value == "size" => spec.sort_by_size = true,
             "since" => {
                 if let Ok(ts) = mailparse::dateparse(value) {
                     spec.since_ts = Some(ts);
@@ -578,6 +705,8 @@ struct App {
     render_pending_tile_height_px: i64,
     render_pending_width_px: i64,
     pending_body_fetch: HashSet<i64>,
+    /// Attachments being downloaded, by message id and IMAP section.
+    pending_part_fetch: HashSet<(i64, String)>,
     image_picker: Option<Picker>,
     image_protocol: Option<StatefulProtocol>,
     protocol_cache: HashMap<(i64, usize), StatefulProtocol>,
@@ -612,6 +741,7 @@ struct App {
     search_query: String,
     search_cursor: usize,
     search_spec: SearchSpec,
+    server_search: Option<ServerSearch>,
     search_attachment_queue: VecDeque<i64>,
     attachment_checked: HashSet<i64>,
     attachment_cache: HashMap<i64, Vec<AttachmentMeta>>,
@@ -619,6 +749,8 @@ struct App {
     bulk_action_ids: Vec<i64>,
     bulk_folder_index: usize,
     bulk_done_return: Mode,
+    tag_input: String,
+    tag_index: usize,
     confirm_delete_ids: Vec<i64>,
     confirm_delete_return: Mode,
     confirm_link: Option<LinkInfo>,
@@ -627,6 +759,9 @@ struct App {
     confirm_compose_action: Option<ComposeStartAction>,
     confirm_compose_attachment_count: usize,
     confirm_compose_return: Mode,
+    /// Certificate the user is asked to trust on first use.
+    confirm_certificate: Option<UntrustedCertificate>,
+    confirm_certificate_return: Mode,
     picker_mode: Option<PickerMode>,
     picker_focus: PickerFocus,
     picker: Option<FileExplorer>,
@@ -644,12 +779,20 @@ struct App {
     picker_preview_error: Option<String>,
     image_resize_prompt: Option<ImageResizePrompt>,
     imap_enabled: bool,
+    permanent_delete: bool,
+    /// Keywords are Gmail labels, which may contain spaces. Guessed from the
+    /// host until the server reports whether it has X-GM-EXT-1.
+    gmail_labels: bool,
     last_folder_sync: Option<(String, Instant)>,
     last_backfill: Option<(String, Instant)>,
     imap_pending: usize,
     imap_spinner: usize,
     imap_status: Option<String>,
     initial_sync_days: i64,
+    /// Per-folder `initial_sync_days` overrides.
+    folder_sync_days: HashMap<String, i64>,
+    sync_poll: SyncPoll,
+    pending_ops: PendingOps,
     compose_to: String,
     compose_from: String,
     compose_cc: String,
@@ -658,6 +801,15 @@ struct App {
     compose_body: ComposeBuffer,
     compose_quote: String,
     compose_attachments: Vec<ComposeAttachment>,
+    /// Forwarded attachments still downloading, by source message, IMAP
+    /// section and attachment index; attached when the part arrives.
+    compose_pending_parts: Vec<(i64, String, usize)>,
+    /// Local id of the draft reopened in compose; replaced on save.
+    compose_draft_id: Option<i64>,
+    /// Draft being sent; discarded once SMTP accepts the message.
+    sending_draft_id: Option<i64>,
+    /// Queued op of the message sent from compose.
+    sending_op: Option<i64>,
     compose_vim_enabled: bool,
     compose_vim_mode: ComposeVimMode,
     compose_vim_pending: Option<char>,
@@ -802,10 +954,67 @@ fn apply_input_key(target: &mut String, cursor: &mut usize, key: KeyEvent) -> bo
     false
 }
 
-fn build_sync_update(items: &[MessageSummary]) -> Option<SyncUpdate> {
-    if items.is_empty() {
-        return None;
+fn message_summary_from_imap(m: ImapMessageSummary) -> MessageSummary {
+    MessageSummary {
+        id: 0,
+        folder_id: 0,
+        imap_uid: Some(m.uid),
+        date: m.date,
+        from: m.from,
+        subject: m.subject,
+        unread: m.unread,
+        flagged: m.flagged,
+        keywords: m.keywords,
+        gmail_msgid: m.gmail_msgid,
+        gmail_thread_id: m.gmail_thread_id,
+        preview: m.preview,
+        to: m.to,
+        cc: m.cc,
+        message_id_header: m.message_id_header,
+        in_reply_to: m.in_reply_to,
+        references: m.references,
+        reply_to: m.reply_to,
+        list_id: m.list_id,
+        size: m.size,
+    }
+}
+
+/// Incremental sync modes for every cached folder, for `MailCommand::SyncAll`.
+/// Folders that were never synced are left out and start with an initial sync.
+fn sync_resume_modes(
+    runtime: &tokio::runtime::Runtime,
+    store: &SqliteMailStore,
+    folders: &[Folder],
+) -> HashMap<String, ratmail_mail::SyncMode> {
+    let mut resume = HashMap::new();
+    for folder in folders {
+        let Some(state) = runtime
+            .block_on(store.get_folder_sync_state(folder.id))
+            .ok()
+            .flatten()
+        else {
+            continue;
+        };
+        if let Some(uid) = state.last_seen_uid {
+            resume.insert(
+                folder.name.clone(),
+                ratmail_mail::SyncMode::Incremental {
+                    last_seen_uid: uid as u32,
+                    highest_modseq: state.highest_modseq.map(|v| v as u64),
+                },
+            );
+        }
     }
+    resume
+}
+
+/// `reconciled` marks syncs that also applied flag/expunge changes for the
+/// cached UIDs; only those may advance the stored HIGHESTMODSEQ.
+fn build_sync_update(
+    items: &[MessageSummary],
+    mailbox: ImapMailboxState,
+    reconciled: bool,
+) -> SyncUpdate {
     let mut last_seen_uid: Option<i64> = None;
     let mut oldest_ts: Option<i64> = None;
     for item in items {
@@ -820,11 +1029,17 @@ fn build_sync_update(items: &[MessageSummary]) -> Option<SyncUpdate> {
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
-    Some(SyncUpdate {
+    SyncUpdate {
+        uidvalidity: mailbox.uidvalidity.map(i64::from),
+        uidnext: mailbox.uidnext.map(i64::from),
+        highest_modseq: mailbox
+            .highest_modseq
+            .filter(|_| reconciled)
+            .and_then(|v| i64::try_from(v).ok()),
         last_seen_uid,
         oldest_ts,
         last_sync_ts,
-    })
+    }
 }
 
 fn text_char_len(text: &str) -> usize {
@@ -1709,6 +1924,9 @@ fn main() -> Result<()> {
     if let Err(err) = ensure_default_config_exists() {
         log_debug(&format!("config bootstrap failed: {}", err));
     }
+    if let Err(err) = ratmail_mail::load_known_certificates(&known_certificates_path()) {
+        log_debug(&format!("known certificates load failed: {}", err));
+    }
     let mut accounts = load_accounts_config();
     let (cli_requested, cli_command) = match resolve_cli_command(cli) {
         Ok(result) => result,
@@ -1832,6 +2050,8 @@ fn main() -> Result<()> {
                 let (store_snapshot_tx, store_updates) =
                     tokio::sync::mpsc::channel::<StoreSnapshot>(STORE_SNAPSHOT_QUEUE_CAPACITY);
                 let store_for_task = store_handle.clone();
+                // Drafts are mirrored to the server only when IMAP is configured.
+                let draft_engine = account.imap.as_ref().map(|_| engine.clone());
                 tokio::spawn(async move {
                     while let Some(update) = store_update_rx.recv().await {
                         let result: Result<StoreSnapshot, anyhow::Error> = (|| async {
@@ -1857,7 +2077,9 @@ fn main() -> Result<()> {
                                     account_id,
                                     folder_name,
                                     messages,
-                                    sync_update,
+                                    sync_update: update,
+                                    invalidate,
+                                    changes,
                                 } => {
                                     let mut folder_id = store_for_task
                                         .folder_id_by_name(account_id, &folder_name)
@@ -1868,6 +2090,7 @@ fn main() -> Result<()> {
                                             account_id,
                                             name: folder_name.clone(),
                                             unread: 0,
+                                            role: None,
                                         };
                                         store_for_task
                                             .upsert_folders(account_id, &[fallback])
@@ -1877,6 +2100,13 @@ fn main() -> Result<()> {
                                             .await?;
                                     }
                                     if let Some(folder_id) = folder_id {
+                                        if invalidate {
+                                            log_debug(&format!(
+                                                "store_update invalidate folder={} id={}",
+                                                folder_name, folder_id
+                                            ));
+                                            store_for_task.invalidate_folder(folder_id).await?;
+                                        }
                                         let items: Vec<MessageSummary> = messages
                                             .into_iter()
                                             .map(|mut m| {
@@ -1889,46 +2119,58 @@ fn main() -> Result<()> {
                                                 account_id, folder_id, &items,
                                             )
                                             .await?;
-                                        if let Some(update) = sync_update {
-                                            let existing = store_for_task
-                                                .get_folder_sync_state(folder_id)
-                                                .await?
-                                                .unwrap_or(FolderSyncState {
-                                                    folder_id,
-                                                    uidvalidity: None,
-                                                    uidnext: None,
-                                                    last_seen_uid: None,
-                                                    last_sync_ts: None,
-                                                    oldest_ts: None,
-                                                });
-                                            let merged = FolderSyncState {
-                                                folder_id,
-                                                uidvalidity: existing.uidvalidity,
-                                                uidnext: existing.uidnext,
-                                                last_seen_uid: match (
-                                                    existing.last_seen_uid,
-                                                    update.last_seen_uid,
-                                                ) {
-                                                    (Some(a), Some(b)) => Some(a.max(b)),
-                                                    (Some(a), None) => Some(a),
-                                                    (None, Some(b)) => Some(b),
-                                                    (None, None) => None,
-                                                },
-                                                last_sync_ts: Some(update.last_sync_ts),
-                                                oldest_ts: match (
-                                                    existing.oldest_ts,
-                                                    update.oldest_ts,
-                                                ) {
-                                                    (Some(a), Some(b)) => Some(a.min(b)),
-                                                    (Some(a), None) => Some(a),
-                                                    (None, Some(b)) => Some(b),
-                                                    (None, None) => None,
-                                                },
-                                            };
+                                        if let Some(changes) = changes {
                                             store_for_task
-                                                .upsert_folder_sync_state(&merged)
+                                                .reconcile_folder_messages(
+                                                    folder_id,
+                                                    changes.upto_uid,
+                                                    &changes.vanished,
+                                                    changes.remaining.as_deref(),
+                                                    &changes.flags,
+                                                    &changes.keywords,
+                                                )
                                                 .await?;
                                         }
+                                        let existing = store_for_task
+                                            .get_folder_sync_state(folder_id)
+                                            .await?
+                                            .unwrap_or(FolderSyncState {
+                                                folder_id,
+                                                uidvalidity: None,
+                                                uidnext: None,
+                                                last_seen_uid: None,
+                                                last_sync_ts: None,
+                                                oldest_ts: None,
+                                                highest_modseq: None,
+                                            });
+                                        let merged = FolderSyncState {
+                                            folder_id,
+                                            uidvalidity: update
+                                                .uidvalidity
+                                                .or(existing.uidvalidity),
+                                            uidnext: update.uidnext.or(existing.uidnext),
+                                            highest_modseq: update
+                                                .highest_modseq
+                                                .or(existing.highest_modseq),
+                                            last_seen_uid: match (
+                                                existing.last_seen_uid,
+                                                update.last_seen_uid,
+                                            ) {
+                                                (Some(a), Some(b)) => Some(a.max(b)),
+                                                (Some(a), None) => Some(a),
+                                                (None, Some(b)) => Some(b),
+                                                (None, None) => None,
+                                            },
+                                            last_sync_ts: Some(update.last_sync_ts),
+                                            oldest_ts: match (existing.oldest_ts, update.oldest_ts)
+                                            {
+                                                (Some(a), Some(b)) => Some(a.min(b)),
+                                                (Some(a), None) => Some(a),
+                                                (None, Some(b)) => Some(b),
+                                                (None, None) => None,
+                                            },
+                                        };
+                                        store_for_task.upsert_folder_sync_state(&merged).await?;
                                         log_debug(&format!(
                                             "store_update messages folder={} id={} count={}",
                                             folder_name,
@@ -1958,9 +2200,12 @@ fn main() -> Result<()> {
                                     account_id,
                                     message_id,
                                     raw,
+                                    missing_parts,
                                     cached_text,
                                 } => {
-                                    store_for_task.upsert_raw_body(message_id, &raw).await?;
+                                    store_for_task
+                                        .upsert_partial_body(message_id, &raw, &missing_parts)
+                                        .await?;
                                     let text = cached_text.or_else(|| {
                                         extract_display(&raw, DEFAULT_TEXT_WIDTH as usize)
                                             .ok()
@@ -1987,6 +2232,35 @@ fn main() -> Result<()> {
                                     };
                                     store_for_task.load_snapshot(account_id, folder_id).await
                                 }
+                                StoreUpdate::BodyPart {
+                                    account_id,
+                                    message_id,
+                                    section,
+                                    data,
+                                } => {
+                                    if let Some(raw) =
+                                        store_for_task.get_raw_body(message_id).await?
+                                    {
+                                        let raw = insert_body_part(&raw, &section, &data)?;
+                                        let mut missing =
+                                            store_for_task.get_missing_parts(message_id).await?;
+                                        missing.retain(|missing| *missing != section);
+                                        store_for_task
+                                            .upsert_partial_body(message_id, &raw, &missing)
+                                            .await?;
+                                    }
+                                    let folder_id = match store_for_task
+                                        .folder_id_by_name(account_id, "INBOX")
+                                        .await?
+                                    {
+                                        Some(id) => id,
+                                        None => store_for_task
+                                            .first_folder_id(account_id)
+                                            .await?
+                                            .unwrap_or(1),
+                                    };
+                                    store_for_task.load_snapshot(account_id, folder_id).await
+                                }
                                 StoreUpdate::MoveMessages {
                                     account_id,
                                     message_ids,
@@ -2003,9 +2277,59 @@ fn main() -> Result<()> {
                                 StoreUpdate::DeleteMessages {
                                     account_id,
                                     message_ids,
+                                    to_trash,
                                     refresh_folder_id,
                                 } => {
-                                    store_for_task.delete_messages(&message_ids).await?;
+                                    if to_trash {
+                                        let trash_id = store_for_task
+                                            .ensure_folder_for_role(account_id, FolderRole::Trash)
+                                            .await?;
+                                        store_for_task
+                                            .move_messages(&message_ids, trash_id)
+                                            .await?;
+                                    } else {
+                                        store_for_task.delete_messages(&message_ids).await?;
+                                    }
+                                    store_for_task
+                                        .load_snapshot(account_id, refresh_folder_id)
+                                        .await
+                                }
+                                StoreUpdate::RemapMovedUids {
+                                    account_id,
+                                    source_folder,
+                                    folder_name,
+                                    uids,
+                                    refresh_folder_id,
+                                } => {
+                                    if let Some(source_id) = store_for_task
+                                        .folder_id_by_name(account_id, &source_folder)
+                                        .await?
+                                        && let Some(folder_id) = store_for_task
+                                            .folder_id_by_name(account_id, &folder_name)
+                                            .await?
+                                    {
+                                        store_for_task
+                                            .remap_moved_uids(source_id, folder_id, &uids)
+                                            .await?;
+                                    }
+                                    store_for_task
+                                        .load_snapshot(account_id, refresh_folder_id)
+                                        .await
+                                }
+                                StoreUpdate::RestoreMovedMessages {
+                                    account_id,
+                                    source_folder,
+                                    uids,
+                                    refresh_folder_id,
+                                } => {
+                                    if let Some(source_id) = store_for_task
+                                        .folder_id_by_name(account_id, &source_folder)
+                                        .await?
+                                    {
+                                        store_for_task
+                                            .restore_moved_messages(source_id, &uids)
+                                            .await?;
+                                    }
                                     store_for_task
                                         .load_snapshot(account_id, refresh_folder_id)
                                         .await
@@ -2025,6 +2349,59 @@ fn main() -> Result<()> {
                                         .load_snapshot(account_id, refresh_folder_id)
                                         .await
                                 }
+                                StoreUpdate::SetMessagesFlagged {
+                                    account_id,
+                                    message_ids,
+                                    flagged,
+                                    refresh_folder_id,
+                                } => {
+                                    for message_id in message_ids {
+                                        store_for_task
+                                            .set_message_flagged(message_id, flagged)
+                                            .await?;
+                                    }
+                                    store_for_task
+                                        .load_snapshot(account_id, refresh_folder_id)
+                                        .await
+                                }
+                                StoreUpdate::SearchResults {
+                                    account_id,
+                                    folder_id,
+                                    messages,
+                                    refresh_folder_id,
+                                } => {
+                                    let items: Vec<MessageSummary> = messages
+                                        .into_iter()
+                                        .map(|mut m| {
+                                            m.folder_id = folder_id;
+                                            m
+                                        })
+                                        .collect();
+                                    store_for_task
+                                        .upsert_folder_messages_append(
+                                            account_id, folder_id, &items,
+                                        )
+                                        .await?;
+                                    store_for_task
+                                        .load_snapshot(account_id, refresh_folder_id)
+                                        .await
+                                }
+                                StoreUpdate::UpdateMessageKeywords {
+                                    account_id,
+                                    message_ids,
+                                    add,
+                                    remove,
+                                    refresh_folder_id,
+                                } => {
+                                    for message_id in message_ids {
+                                        store_for_task
+                                            .update_message_keywords(message_id, &add, &remove)
+                                            .await?;
+                                    }
+                                    store_for_task
+                                        .load_snapshot(account_id, refresh_folder_id)
+                                        .await
+                                }
                                 StoreUpdate::SaveDraft {
                                     account_id,
                                     from_addr,
@@ -2033,22 +2410,136 @@ fn main() -> Result<()> {
                                     bcc,
                                     subject,
                                     body,
+                                    replaces,
+                                    send_op,
                                 } => {
-                                    store_for_task
+                                    let replaced = match replaces {
+                                        Some(id) => store_for_task.get_message_summary(id).await?,
+                                        None => None,
+                                    };
+                                    if let Some(old) = &replaced {
+                                        store_for_task.delete_messages(&[old.id]).await?;
+                                    }
+                                    let message_id = store_for_task
                                         .save_draft(
                                             account_id, &from_addr, &to, &cc, &bcc, &subject, &body,
                                         )
                                         .await?;
+                                    if let Some(op_id) = send_op {
+                                        store_for_task
+                                            .set_pending_op_draft(op_id, message_id)
+                                            .await?;
+                                    }
+                                    let folder_id = store_for_task
+                                        .ensure_folder_for_role(account_id, FolderRole::Drafts)
+                                        .await?;
+                                    if let Some(engine) = &draft_engine
+                                        && let Some(folder) =
+                                            store_for_task.get_folder(folder_id).await?
+                                    {
+                                        let replace_uid = replaced
+                                            .filter(|old| old.folder_id == folder_id)
+                                            .and_then(|old| old.imap_uid);
+                                        let queued = queue_mail_command(
+                                            &store_for_task,
+                                            account_id,
+                                            engine,
+                                            MailCommand::SaveDraft {
+                                                message_id,
+                                                folder_name: folder.name,
+                                                draft: OutgoingDraft {
+                                                    from: from_addr,
+                                                    to,
+                                                    cc,
+                                                    bcc,
+                                                    subject,
+                                                    body,
+                                                },
+                                                replace_uid,
+                                            },
+                                        )
+                                        .await;
+                                        if let Err(err) = queued {
+                                            log_debug(&format!("draft upload not queued: {}", err));
+                                        }
+                                    }
+                                    store_for_task.load_snapshot(account_id, folder_id).await
+                                }
+                                StoreUpdate::DraftUploaded {
+                                    account_id,
+                                    message_id,
+                                    uid,
+                                    raw,
+                                } => {
+                                    store_for_task
+                                        .mark_draft_uploaded(message_id, uid, &raw)
+                                        .await?;
+                                    let folder_id = store_for_task
+                                        .ensure_folder_for_role(account_id, FolderRole::Drafts)
+                                        .await?;
+                                    store_for_task.load_snapshot(account_id, folder_id).await
+                                }
+                                StoreUpdate::DiscardDraft {
+                                    account_id,
+                                    message_id,
+                                } => {
+                                    let draft =
+                                        store_for_task.get_message_summary(message_id).await?;
+                                    if let Some(draft) = draft {
+                                        store_for_task.delete_messages(&[draft.id]).await?;
+                                        if let Some(engine) = &draft_engine
+                                            && let Some(uid) = draft.imap_uid
+                                            && let Some(folder) =
+                                                store_for_task.get_folder(draft.folder_id).await?
+                                        {
+                                            let command = MailCommand::DeleteMessages {
+                                                folder_name: folder.name,
+                                                uids: vec![uid],
+                                                trash_folder: None,
+                                            };
+                                            let queued = queue_mail_command(
+                                                &store_for_task,
+                                                account_id,
+                                                engine,
+                                                command,
+                                            )
+                                            .await;
+                                            if let Err(err) = queued {
+                                                log_debug(&format!(
+                                                    "draft delete not queued: {}",
+                                                    err
+                                                ));
+                                            }
+                                        }
+                                    }
+                                    let folder_id = store_for_task
+                                        .ensure_folder_for_role(account_id, FolderRole::Drafts)
+                                        .await?;
+                                    store_for_task.load_snapshot(account_id, folder_id).await
+                                }
+                                StoreUpdate::SaveSent {
+                                    account_id,
+                                    folder_name,
+                                    uid,
+                                    raw,
+                                } => {
                                     let folder_id = match store_for_task
-                                        .folder_id_by_name(account_id, "Drafts")
+                                        .folder_id_by_name(account_id, &folder_name)
                                         .await?
                                     {
                                         Some(id) => id,
-                                        None => store_for_task
-                                            .first_folder_id(account_id)
-                                            .await?
-                                            .unwrap_or(1),
+                                        None => {
+                                            store_for_task
+                                                .ensure_folder_for_role(
+                                                    account_id,
+                                                    FolderRole::Sent,
+                                                )
+                                                .await?
+                                        }
                                     };
+                                    store_for_task
+                                        .save_sent_message(account_id, folder_id, uid, &raw)
+                                        .await?;
                                     store_for_task.load_snapshot(account_id, folder_id).await
                                 }
                             }
@@ -2108,6 +2599,11 @@ fn main() -> Result<()> {
             .as_ref()
             .map(|i| i.initial_sync_days)
             .unwrap_or(90);
+        let folder_sync_days = account
+            .imap
+            .as_ref()
+            .map(|i| i.folder_sync_days.clone())
+            .unwrap_or_default();
         let app = App::new(
             store,
             store_handle,
@@ -2126,7 +2622,14 @@ fn main() -> Result<()> {
             render_tile_height_px_side,
             render_tile_height_px_focus,
             account.imap.is_some(),
+            account
+                .imap
+                .as_ref()
+                .is_some_and(|imap| imap.permanent_delete),
+            account.imap.as_ref().is_some_and(ImapConfig::is_gmail),
             initial_sync_days,
+            folder_sync_days,
+            SyncPoll::from_config(account.imap.as_ref()),
             render_scale,
             ui_config.folder_width_cols,
             ui_config.theme.clone(),
@@ -2215,12 +2718,14 @@ fn ui(frame: &mut ratatui::Frame, multi: &mut MultiApp) {
         Mode::OverlayAttach => render_attach_overlay(frame, area, app),
         Mode::OverlayBulkAction => render_bulk_action_overlay(frame, area, app),
         Mode::OverlayBulkMove => render_bulk_move_overlay(frame, area, app),
+        Mode::OverlayTags => render_tags_overlay(frame, area, app),
         Mode::OverlayConfirmDelete => render_confirm_delete_overlay(frame, area, app),
         Mode::OverlayConfirmLink => render_confirm_link_overlay(frame, area, app),
         Mode::OverlayConfirmDraft => render_confirm_draft_overlay(frame, area, app),
         Mode::OverlayConfirmComposeAttachments => {
             render_confirm_compose_attachments_overlay(frame, area, app)
         }
+        Mode::OverlayTrustCertificate => render_trust_certificate_overlay(frame, area, app),
         Mode::OverlaySpellcheck => render_spellcheck_overlay(frame, area, app),
         Mode::OverlaySearch => render_search_overlay(frame, area, app),
         Mode::Compose => render_compose_overlay(frame, area, app),
@@ -2268,6 +2773,9 @@ fn render_status_bar(
     }
     spans.push(Span::raw(format!(" acct: {} ", app.store.account.address)));
     spans.push(Span::raw(format!(" sync: {} ", app.sync_status)));
+    if let Some(queue) = app.pending_ops.status_label(Instant::now()) {
+        spans.push(Span::raw(format!(" {} ", queue)));
+    }
     spans.push(Span::styled(
         format!(" view: {} (v) ", view_label),
         app.ui_theme.status_view,
@@ -2338,7 +2846,7 @@ fn render_folders(frame: &mut ratatui::Frame, area: Rect, app: &App) {
         .enumerate()
         .map(|(idx, folder)| {
             let global_idx = start + idx;
-            let display_name = App::display_folder_name(&folder.name);
+            let display_name = App::display_folder_name(folder);
             let label = if folder.unread > 0 {
                 format!("{}  {}", display_name, folder.unread)
             } else {
@@ -2415,7 +2923,8 @@ fn render_message_list(frame: &mut ratatui::Frame, area: Rect, app: &mut App) {
             let selected = app.selected_message_ids.contains(&message.id);
             let sel = if selected { "x" } else { " " };
             let unread = if message.unread { "*" } else { " " };
-            let marker = format!("{}{}", sel, unread);
+            let flagged = if message.flagged { "!" } else { " " };
+            let marker = format!("{}{}{}", sel, unread, flagged);
             let mut style = if global_idx == app.message_index {
                 if app.focus == Focus::Messages {
                     app.ui_theme.focus_bg
@@ -2440,12 +2949,28 @@ fn render_message_list(frame: &mut ratatui::Frame, area: Rect, app: &mut App) {
             } else {
                 " "
             };
+            let mut subject = Vec::new();
+            for keyword in message.keywords.iter().filter(|k| !keyword_hidden(k)) {
+                subject.push(Span::styled(
+                    format!(" {} ", keyword_label(keyword)),
+                    Style::default().fg(Color::Black).bg(keyword_color(keyword)),
+                ));
+                subject.push(Span::raw(" "));
+            }
+            subject.push(Span::raw(message.subject.clone()));
+            // Rows synced before snippets were fetched carry the subject.
+            if !message.preview.is_empty() && message.preview != message.subject {
+                subject.push(Span::styled(
+                    format!("  {}", message.preview),
+                    Style::default().add_modifier(Modifier::DIM),
+                ));
+            }
             Row::new(vec![
-                marker,
-                att.to_string(),
-                message.date.clone(),
-                from_display,
-                message.subject.clone(),
+                Cell::from(marker),
+                Cell::from(att),
+                Cell::from(message.date.clone()),
+                Cell::from(from_display),
+                Cell::from(Line::from(subject)),
             ])
             .style(style)
         })
@@ -2454,7 +2979,7 @@ fn render_message_list(frame: &mut ratatui::Frame, area: Rect, app: &mut App) {
     let table = Table::new(
         rows,
         [
-            Constraint::Length(2),
+            Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(16),
             Constraint::Length(24),
@@ -2554,6 +3079,15 @@ fn canonical_folder_name(raw: &str) -> String {
     mapped.to_string()
 }
 
+/// Folders with a special-use role are labelled by role, so "[Gmail]/Gesendet"
+/// and "Sent Items" both show up as "Sent".
+fn folder_display_name(folder: &Folder) -> String {
+    match folder.role {
+        Some(role) => role.default_name().to_string(),
+        None => canonical_folder_name(&folder.name),
+    }
+}
+
 fn scale_image(img: image::DynamicImage, scale: f64) -> image::DynamicImage {
     if (scale - 1.0).abs() < 0.01 {
         return img;
@@ -2821,7 +3355,10 @@ fn candidate_score(candidate: &str) -> i32 {
 mod tests {
     use mailparse::ParsedMail;
 
-    use super::{SendConfig, build_html_body, detect_auth_code};
+    use super::{
+        OAuth2Mechanism, PasswordSource, SendConfig, build_html_body, detect_auth_code,
+        parse_account_config,
+    };
 
     #[test]
     fn detects_numeric_verification_code() {
@@ -2901,6 +3438,135 @@ Content-Type: text/html; charset=utf-8\r\n\
         assert!(html_body.contains("Kind regards,<br>"));
     }
 
+    #[test]
+    fn oauth2_accounts_do_not_need_a_password() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            name = "Work"
+            auth = "oauth2"
+
+            [oauth2]
+            token_file = "/tmp/work-token.json"
+            client_id = "client"
+
+            [imap]
+            host = "imap.gmail.com"
+            username = "me@example.com"
+            "#,
+        )
+        .unwrap();
+        let account = parse_account_config(&value, 0).unwrap();
+        let imap = account.imap.unwrap();
+        let oauth2 = imap.oauth2.unwrap();
+        assert_eq!(oauth2.mechanism, OAuth2Mechanism::Xoauth2);
+        assert_eq!(oauth2.token_file.as_deref(), Some("/tmp/work-token.json"));
+        assert_eq!(
+            oauth2.token_url.as_deref(),
+            Some("https://oauth2.googleapis.com/token")
+        );
+
+        let password_only: toml::Value =
+            toml::from_str("[imap]\nhost = \"imap.example.com\"\nusername = \"me\"").unwrap();
+        let account = parse_account_config(&password_only, 0).unwrap();
+        assert!(account.imap.is_none());
+    }
+
+    #[test]
+    fn passwords_come_from_command_keyring_or_config() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            name = "Work"
+
+            [imap]
+            host = "imap.example.com"
+            username = "me@example.com"
+            password = "ignored"
+            password_command = "pass show mail/work"
+
+            [smtp]
+            host = "smtp.example.com"
+            username = "me@example.com"
+            from = "me@example.com"
+            password_keyring = true
+            "#,
+        )
+        .unwrap();
+        let account = parse_account_config(&value, 0).unwrap();
+        assert_eq!(
+            account.imap.unwrap().password,
+            PasswordSource::Command("pass show mail/work".to_string())
+        );
+        assert_eq!(
+            account.smtp.unwrap().password,
+            PasswordSource::Keyring {
+                service: "ratmail".to_string(),
+                account: "smtp.example.com:me@example.com".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn account_proxy_applies_to_both_servers_unless_overridden() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            proxy = "socks5://127.0.0.1:9050"
+
+            [imap]
+            host = "imap.example.com"
+            username = "me@example.com"
+            password = "secret"
+
+            [smtp]
+            host = "smtp.example.com"
+            username = "me@example.com"
+            from = "me@example.com"
+            password = "secret"
+            proxy = "http://proxy.corp:3128"
+            "#,
+        )
+        .unwrap();
+        let account = parse_account_config(&value, 0).unwrap();
+        assert_eq!(
+            account.imap.unwrap().proxy.as_deref(),
+            Some("socks5://127.0.0.1:9050")
+        );
+        assert_eq!(
+            account.smtp.unwrap().proxy.as_deref(),
+            Some("http://proxy.corp:3128")
+        );
+    }
+
+    #[test]
+    fn folder_sync_order_and_windows_come_from_imap_table() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            [imap]
+            host = "imap.example.com"
+            username = "me@example.com"
+            password = "secret"
+            initial_sync_days = 30
+            sync_priority = ["INBOX", "sent", "Projects"]
+            sync_exclude = ["Newsletters"]
+            poll_interval_secs = 10
+
+            [imap.sync_days]
+            Archive = 365
+
+            [imap.poll_intervals]
+            Archive = 0
+            "#,
+        )
+        .unwrap();
+        let imap = parse_account_config(&value, 0).unwrap().imap.unwrap();
+        assert_eq!(imap.sync_priority, vec!["INBOX", "sent", "Projects"]);
+        assert_eq!(imap.sync_exclude, vec!["Newsletters"]);
+        assert_eq!(imap.sync_days_for("Archive"), 365);
+        assert_eq!(imap.sync_days_for("INBOX"), 30);
+        assert_eq!(imap.poll_interval_secs, 30);
+        assert_eq!(imap.folder_poll_secs.get("Archive"), Some(&0));
+        assert_eq!(imap.folder_poll_secs.len(), 1);
+    }
+
     fn find_part<'a>(parsed: &'a ParsedMail<'a>, mime: &str) -> Option<&'a ParsedMail<'a>> {
         for part in &parsed.subparts {
             if part.ctype.mimetype.eq_ignore_ascii_case(mime) {
@@ -3474,6 +4140,11 @@ fn default_db_dir() -> PathBuf {
     dir
 }
 
+/// Certificate pins the user accepted on first connect.
+fn known_certificates_path() -> PathBuf {
+    default_db_dir().join("known_certificates")
+}
+
 fn resolve_db_path(raw: &str) -> String {
     let path = Path::new(raw);
     if path.is_absolute() {
@@ -3499,8 +4170,9 @@ fn load_accounts_config() -> Vec<AccountConfig> {
             .filter_map(|(idx, acct)| parse_account_config(acct, idx))
             .collect();
     }
-    let smtp = parse_smtp_config(&value);
-    let imap = parse_imap_config(&value);
+    let oauth2 = parse_oauth2_config(&value);
+    let smtp = parse_smtp_config(&value, oauth2.as_ref());
+    let imap = parse_imap_config(&value, oauth2.as_ref());
     if smtp.is_none() && imap.is_none() {
         return Vec::new();
     }
@@ -3524,8 +4196,9 @@ fn parse_account_config(value: &toml::Value, index: usize) -> Option<AccountConf
         .get("name")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    let smtp = value.get("smtp").and_then(parse_smtp_table);
-    let imap = value.get("imap").and_then(parse_imap_table);
+    let oauth2 = parse_oauth2_config(value);
+    let smtp = parse_smtp_config(value, oauth2.as_ref());
+    let imap = parse_imap_config(value, oauth2.as_ref());
     let derived = name
         .clone()
         .or_else(|| imap.as_ref().map(|i| i.username.clone()))
@@ -3583,38 +4256,173 @@ fn normalize_sender_identity(raw: &str) -> Option<String> {
     }
 }
 
-fn parse_smtp_config(value: &toml::Value) -> Option<SmtpConfig> {
-    value.get("smtp").and_then(parse_smtp_table)
+/// `auth = "oauth2"` and its `[oauth2]` table; `None` means password login.
+fn parse_oauth2_config(value: &toml::Value) -> Option<OAuth2Config> {
+    let auth = value
+        .get("auth")
+        .and_then(|v| v.as_str())
+        .unwrap_or("password");
+    if !auth.trim().eq_ignore_ascii_case("oauth2") {
+        return None;
+    }
+    let table = value.get("oauth2");
+    let text = |key: &str| {
+        table
+            .and_then(|t| t.get(key))
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let imap_host = value
+        .get("imap")
+        .and_then(|imap| imap.get("host"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    Some(OAuth2Config {
+        mechanism: text("mechanism")
+            .as_deref()
+            .and_then(OAuth2Mechanism::parse)
+            .unwrap_or_default(),
+        token_command: text("token_command"),
+        token_file: text("token_file").map(|path| resolve_db_path(&path)),
+        token_url: text("token_url")
+            .or_else(|| default_oauth2_token_url(imap_host).map(str::to_string)),
+        client_id: text("client_id"),
+        client_secret: text("client_secret"),
+    })
+}
+
+/// Token endpoints of the providers that require OAuth2 most often.
+fn default_oauth2_token_url(imap_host: &str) -> Option<&'static str> {
+    let host = imap_host.to_ascii_lowercase();
+    if host.ends_with("gmail.com") || host.ends_with("googlemail.com") {
+        Some("https://oauth2.googleapis.com/token")
+    } else if host.ends_with("office365.com") || host.ends_with("outlook.com") {
+        Some("https://login.microsoftonline.com/common/oauth2/v2.0/token")
+    } else {
+        None
+    }
+}
+
+/// `password_command`, `password_keyring` or an inline `password`, in that
+/// order; OAuth2 accounts need none of them.
+fn parse_password(table: &toml::Value, oauth2: Option<&OAuth2Config>) -> Option<PasswordSource> {
+    if let Some(command) = table
+        .get("password_command")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+    {
+        return Some(PasswordSource::Command(command.to_string()));
+    }
+    let keyring_entry = match table.get("password_keyring") {
+        Some(toml::Value::Boolean(true)) => Some(keyring_account(
+            table.get("host")?.as_str()?,
+            table.get("username")?.as_str()?,
+        )),
+        Some(toml::Value::String(entry)) if !entry.trim().is_empty() => {
+            Some(entry.trim().to_string())
+        }
+        _ => None,
+    };
+    if let Some(account) = keyring_entry {
+        return Some(PasswordSource::Keyring {
+            service: KEYRING_SERVICE.to_string(),
+            account,
+        });
+    }
+    match table.get("password").and_then(|v| v.as_str()) {
+        Some(password) => Some(PasswordSource::Plain(password.to_string())),
+        None => oauth2.map(|_| PasswordSource::default()),
+    }
+}
+
+/// `proxy = "socks5://..."` on the account, or on `[imap]`/`[smtp]` to
+/// override it for one server. Checked when connecting, so a malformed URL
+/// fails the connection instead of silently going direct.
+fn parse_proxy(table: &toml::Value) -> Option<String> {
+    table
+        .get("proxy")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
 }
 
-fn parse_smtp_table(smtp: &toml::Value) -> Option<SmtpConfig> {
+fn parse_smtp_config(value: &toml::Value, oauth2: Option<&OAuth2Config>) -> Option<SmtpConfig> {
+    let mut smtp = value
+        .get("smtp")
+        .and_then(|smtp| parse_smtp_table(smtp, oauth2))?;
+    smtp.proxy = smtp.proxy.or_else(|| parse_proxy(value));
+    Some(smtp)
+}
+
+fn parse_smtp_table(smtp: &toml::Value, oauth2: Option<&OAuth2Config>) -> Option<SmtpConfig> {
     Some(SmtpConfig {
         host: smtp.get("host")?.as_str()?.to_string(),
         port: smtp.get("port").and_then(|v| v.as_integer()).unwrap_or(587) as u16,
         username: smtp.get("username")?.as_str()?.to_string(),
-        password: smtp.get("password")?.as_str()?.to_string(),
+        password: parse_password(smtp, oauth2)?,
         from: smtp.get("from")?.as_str()?.to_string(),
+        security: smtp
+            .get("security")
+            .and_then(|v| v.as_str())
+            .and_then(TlsSecurity::parse),
         skip_tls_verify: smtp
             .get("skip_tls_verify")
             .and_then(|v| v.as_bool())
             .unwrap_or(false),
+        ca_file: smtp
+            .get("ca_file")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        cert_fingerprint: smtp
+            .get("cert_fingerprint")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string),
+        save_sent: smtp
+            .get("save_sent")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true),
+        oauth2: oauth2.cloned(),
+        proxy: parse_proxy(smtp),
     })
 }
 
-fn parse_imap_config(value: &toml::Value) -> Option<ImapConfig> {
-    value.get("imap").and_then(parse_imap_table)
+fn parse_imap_config(value: &toml::Value, oauth2: Option<&OAuth2Config>) -> Option<ImapConfig> {
+    let mut imap = value
+        .get("imap")
+        .and_then(|imap| parse_imap_table(imap, oauth2))?;
+    imap.proxy = imap.proxy.or_else(|| parse_proxy(value));
+    Some(imap)
 }
 
-fn parse_imap_table(imap: &toml::Value) -> Option<ImapConfig> {
+fn parse_imap_table(imap: &toml::Value, oauth2: Option<&OAuth2Config>) -> Option<ImapConfig> {
     Some(ImapConfig {
         host: imap.get("host")?.as_str()?.to_string(),
         port: imap.get("port").and_then(|v| v.as_integer()).unwrap_or(993) as u16,
         username: imap.get("username")?.as_str()?.to_string(),
-        password: imap.get("password")?.as_str()?.to_string(),
+        password: parse_password(imap, oauth2)?,
+        security: imap
+            .get("security")
+            .and_then(|v| v.as_str())
+            .and_then(TlsSecurity::parse),
         skip_tls_verify: imap
             .get("skip_tls_verify")
             .and_then(|v| v.as_bool())
             .unwrap_or(false),
+        ca_file: imap
+            .get("ca_file")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        cert_fingerprint: imap
+            .get("cert_fingerprint")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string),
         initial_sync_days: imap
             .get("initial_sync_days")
             .and_then(|v| v.as_integer())
@@ -3625,9 +4433,80 @@ fn parse_imap_table(imap: &toml::Value) -> Option<ImapConfig> {
             .and_then(|v| v.as_integer())
             .map(|v| v.clamp(1, 50) as usize)
             .unwrap_or(10),
+        idle_folders: parse_string_list(imap.get("idle_folders"))
+            .unwrap_or_else(|| vec!["INBOX".to_string()]),
+        idle_poll_secs: imap
+            .get("idle_poll_secs")
+            .and_then(|v| v.as_integer())
+            .map(|v| v.max(15) as u64)
+            .unwrap_or(120),
+        folder_roles: imap
+            .get("folders")
+            .and_then(|v| v.as_table())
+            .map(|table| {
+                table
+                    .iter()
+                    .filter_map(|(role, name)| {
+                        let role = FolderRole::parse(role)?;
+                        let name = name.as_str()?.trim();
+                        (!name.is_empty()).then(|| (role, name.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        permanent_delete: imap
+            .get("permanent_delete")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false),
+        oauth2: oauth2.cloned(),
+        proxy: parse_proxy(imap),
+        sync_priority: parse_string_list(imap.get("sync_priority"))
+            .unwrap_or_else(|| vec!["INBOX".to_string()]),
+        sync_exclude: parse_string_list(imap.get("sync_exclude")).unwrap_or_default(),
+        poll_interval_secs: imap
+            .get("poll_interval_secs")
+            .and_then(|v| v.as_integer())
+            .map(poll_secs)
+            .unwrap_or(300),
+        folder_poll_secs: imap
+            .get("poll_intervals")
+            .and_then(|v| v.as_table())
+            .map(|table| {
+                table
+                    .iter()
+                    .filter_map(|(name, secs)| Some((name.clone(), poll_secs(secs.as_integer()?))))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        folder_sync_days: imap
+            .get("sync_days")
+            .and_then(|v| v.as_table())
+            .map(|table| {
+                table
+                    .iter()
+                    .filter_map(|(name, days)| Some((name.clone(), days.as_integer()?.max(1))))
+                    .collect()
+            })
+            .unwrap_or_default(),
     })
 }
 
+/// Polling interval in seconds: 0 (off) or at least 30.
+fn poll_secs(value: i64) -> u64 {
+    if value <= 0 { 0 } else { value.max(30) as u64 }
+}
+
+fn parse_string_list(value: Option<&toml::Value>) -> Option<Vec<String>> {
+    let list = value?.as_array()?;
+    Some(
+        list.iter()
+            .filter_map(|item| item.as_str())
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+    )
+}
+
 fn slugify_name(raw: &str) -> String {
     let mut out = String::new();
     let mut last_dash = false;
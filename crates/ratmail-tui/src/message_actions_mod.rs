@@ -1,8 +1,10 @@
 use ratmail_content::{extract_attachments, extract_display};
-use ratmail_core::{DEFAULT_TEXT_WIDTH, LinkInfo, MailStore, MessageDetail};
+use ratmail_core::{DEFAULT_TEXT_WIDTH, FolderRole, LinkInfo, MailStore, MessageDetail};
 use ratmail_mail::MailCommand;
 
-use super::{App, Mode, StoreUpdate, cc_from_raw, copy_to_clipboard, detect_auth_code, to_from_raw};
+use crate::cli::map_folder_names;
+
+use super::{App, Mode, StoreUpdate, cc_from_raw, copy_to_clipboard, detect_auth_code, keyword_label, to_from_raw};
 
 impl App {
     pub(crate) fn copy_auth_code_for_selected(&mut self) {
@@ -112,7 +114,7 @@ impl App {
                         .find(|f| f.id == target_folder_id)
                         .map(|f| f.name.clone()),
                 ) {
-                    let _ = self.engine.send(MailCommand::MoveMessages {
+                    self.send_mail_command(MailCommand::MoveMessages {
                         folder_name: src_folder,
                         target_folder: dst_folder,
                         uids,
@@ -135,9 +137,11 @@ impl App {
         }
         let account_id = self.store.account.id;
         let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+        let to_trash = self.delete_moves_to_trash();
         self.queue_store_update_reliable(StoreUpdate::DeleteMessages {
             account_id,
             message_ids: ids.clone(),
+            to_trash,
             refresh_folder_id,
         });
 
@@ -145,16 +149,25 @@ impl App {
             let uids = self.collect_imap_uids(&ids);
             if !uids.is_empty() {
                 if let Some(folder_name) = self.selected_folder().map(|f| f.name.clone()) {
-                    let _ = self
-                        .engine
-                        .send(MailCommand::DeleteMessages { folder_name, uids });
+                    // Without a known Trash folder the move fails on the server
+                    // and the messages come back on the next sync.
+                    let trash_folder = to_trash.then(|| {
+                        self.folder_name_by_role(FolderRole::Trash)
+                            .unwrap_or_else(|| FolderRole::Trash.default_name().to_string())
+                    });
+                    self.send_mail_command(MailCommand::DeleteMessages {
+                        folder_name,
+                        uids,
+                        trash_folder,
+                    });
                 }
             }
         }
 
         self.clear_selected_messages();
         self.set_status(format!(
-            "Deleted {} message{}",
+            "{} {} message{}",
+            if to_trash { "Trashed" } else { "Deleted" },
             ids.len(),
             if ids.len() == 1 { "" } else { "s" }
         ));
@@ -166,6 +179,14 @@ impl App {
         }
         let account_id = self.store.account.id;
         let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+        // Only messages that actually change state are sent to the server.
+        let changed: Vec<(i64, i64, Option<u32>)> = self
+            .store
+            .messages
+            .iter()
+            .filter(|m| m.unread && ids.contains(&m.id))
+            .map(|m| (m.id, m.folder_id, m.imap_uid))
+            .collect();
         self.queue_store_update_reliable(StoreUpdate::SetMessagesUnread {
             account_id,
             message_ids: ids,
@@ -173,10 +194,159 @@ impl App {
             refresh_folder_id,
         });
 
+        if self.imap_enabled {
+            // A selection can span folders (search results), so every
+            // message is updated in the folder it lives in.
+            let folder_map = map_folder_names(&self.store.folders);
+            for (message_id, folder_id, uid) in changed {
+                let (Some(uid), Some(folder_name)) = (uid, folder_map.get(&folder_id)) else {
+                    continue;
+                };
+                self.send_mail_command(MailCommand::SetFlag {
+                    message_id,
+                    folder_name: folder_name.clone(),
+                    uid,
+                    seen: true,
+                });
+            }
+        }
+
         self.clear_selected_messages();
         self.set_status("Marked selected messages as read");
     }
 
+    /// Flags every message in `ids`, or unflags them if all are already flagged.
+    pub(crate) fn queue_toggle_flagged_messages(&mut self, ids: Vec<i64>) {
+        if ids.is_empty() {
+            return;
+        }
+        let flagged = self
+            .store
+            .messages
+            .iter()
+            .any(|m| ids.contains(&m.id) && !m.flagged);
+        let account_id = self.store.account.id;
+        let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+        let changed: Vec<(i64, i64, Option<u32>)> = self
+            .store
+            .messages
+            .iter()
+            .filter(|m| m.flagged != flagged && ids.contains(&m.id))
+            .map(|m| (m.id, m.folder_id, m.imap_uid))
+            .collect();
+        self.queue_store_update_reliable(StoreUpdate::SetMessagesFlagged {
+            account_id,
+            message_ids: ids.clone(),
+            flagged,
+            refresh_folder_id,
+        });
+
+        if self.imap_enabled {
+            let folder_map = map_folder_names(&self.store.folders);
+            for (message_id, folder_id, uid) in changed {
+                let (Some(uid), Some(folder_name)) = (uid, folder_map.get(&folder_id)) else {
+                    continue;
+                };
+                self.send_mail_command(MailCommand::SetFlagged {
+                    message_id,
+                    folder_name: folder_name.clone(),
+                    uid,
+                    flagged,
+                });
+            }
+        }
+
+        self.clear_selected_messages();
+        self.set_status(format!(
+            "{} {} message{}",
+            if flagged { "Flagged" } else { "Unflagged" },
+            ids.len(),
+            if ids.len() == 1 { "" } else { "s" }
+        ));
+    }
+
+    /// Adds and removes keywords on every message in `ids`, locally and on the
+    /// server. Only keywords a message actually gains or loses are stored.
+    pub(crate) fn queue_update_keywords(
+        &mut self,
+        ids: Vec<i64>,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) {
+        if ids.is_empty() || (add.is_empty() && remove.is_empty()) {
+            return;
+        }
+        let account_id = self.store.account.id;
+        let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+        let mut changed = Vec::new();
+        for message in self
+            .store
+            .messages
+            .iter_mut()
+            .filter(|m| ids.contains(&m.id))
+        {
+            let gained: Vec<String> = add
+                .iter()
+                .filter(|k| !message.keywords.contains(k))
+                .cloned()
+                .collect();
+            let lost: Vec<String> = remove
+                .iter()
+                .filter(|k| message.keywords.contains(k))
+                .cloned()
+                .collect();
+            if gained.is_empty() && lost.is_empty() {
+                continue;
+            }
+            message.keywords.retain(|k| !lost.contains(k));
+            message.keywords.extend(gained.iter().cloned());
+            message.keywords.sort_unstable();
+            changed.push((
+                message.id,
+                message.folder_id,
+                message.imap_uid,
+                gained,
+                lost,
+            ));
+        }
+        self.queue_store_update_reliable(StoreUpdate::UpdateMessageKeywords {
+            account_id,
+            message_ids: ids.clone(),
+            add: add.clone(),
+            remove: remove.clone(),
+            refresh_folder_id,
+        });
+
+        if self.imap_enabled {
+            let folder_map = map_folder_names(&self.store.folders);
+            for (message_id, folder_id, uid, gained, lost) in changed {
+                let (Some(uid), Some(folder_name)) = (uid, folder_map.get(&folder_id)) else {
+                    continue;
+                };
+                self.send_mail_command(MailCommand::SetKeywords {
+                    message_id,
+                    folder_name: folder_name.clone(),
+                    uid,
+                    add: gained,
+                    remove: lost,
+                });
+            }
+        }
+
+        let summary = add
+            .iter()
+            .map(|k| format!("+{}", keyword_label(k)))
+            .chain(remove.iter().map(|k| format!("-{}", keyword_label(k))))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.set_status(format!(
+            "Tagged {} message{}: {}",
+            ids.len(),
+            if ids.len() == 1 { "" } else { "s" },
+            summary
+        ));
+    }
+
     pub(crate) fn ensure_text_cache_for_selected(&mut self) {
         self.ensure_text_cache_for_selected_width(DEFAULT_TEXT_WIDTH);
     }
@@ -8,7 +8,8 @@ use ratatui_image::{Resize, StatefulImage};
 
 use super::{
     App, ImageResizePreset, PickerFocus, PickerMode, PickerPreviewKind, SpellTarget, centered_rect,
-    format_size, link_display_label, set_cursor_at, spell_issue_context_line, truncate_label,
+    format_size, keyword_color, keyword_label, link_display_label, set_cursor_at,
+    spell_issue_context_line, truncate_label,
 };
 
 pub(crate) fn render_search_overlay(frame: &mut ratatui::Frame, area: Rect, app: &mut App) {
@@ -31,13 +32,19 @@ pub(crate) fn render_search_overlay(frame: &mut ratatui::Frame, area: Rect, app:
             Constraint::Length(1),
         ])
         .split(inner);
-    frame.render_widget(
-        Paragraph::new("Filter messages by From, Subject, Preview").style(app.ui_theme.base),
-        rows[0],
-    );
+    let intro = match &app.server_search {
+        Some(search) if search.pending => {
+            "Filter messages by From, Subject, Preview (searching server...)"
+        }
+        _ if app.imap_enabled => {
+            "Filter messages by From, Subject, Preview; Enter also searches the server"
+        }
+        _ => "Filter messages by From, Subject, Preview",
+    };
+    frame.render_widget(Paragraph::new(intro).style(app.ui_theme.base), rows[0]);
     frame.render_widget(
         Paragraph::new(
-            "Fields: from:alice  subject:invoice  to:bob  date:2026-02-01  since:2026-01-01  before:2026-02-10",
+            "Fields: from:alice  subject:invoice  to:bob  body:refund  date:2026-02-01  since:2026-01-01  before:2026-02-10  gm:older_than:1y",
         )
         .style(app.ui_theme.label),
         rows[1],
@@ -222,7 +229,9 @@ pub(crate) fn render_bulk_action_overlay(frame: &mut ratatui::Frame, area: Rect,
     )));
     lines.push(Line::from(""));
     lines.push(Line::from("r mark read"));
+    lines.push(Line::from("F toggle flag"));
     lines.push(Line::from("m move"));
+    lines.push(Line::from("t tags"));
     lines.push(Line::from("d delete"));
     lines.push(Line::from("Esc close"));
 
@@ -252,7 +261,7 @@ pub(crate) fn render_bulk_move_overlay(frame: &mut ratatui::Frame, area: Rect, a
         } else {
             Style::default()
         };
-        let label = App::display_folder_name(&folder.name);
+        let label = App::display_folder_name(folder);
         lines.push(Line::from(Span::styled(format!("  {}", label), style)));
     }
 
@@ -271,18 +280,81 @@ pub(crate) fn render_bulk_move_overlay(frame: &mut ratatui::Frame, area: Rect, a
     frame.render_widget(paragraph, popup);
 }
 
-pub(crate) fn render_confirm_delete_overlay(frame: &mut ratatui::Frame, area: Rect, app: &App) {
-    let popup = centered_rect(50, 30, area);
+pub(crate) fn render_tags_overlay(frame: &mut ratatui::Frame, area: Rect, app: &App) {
+    let popup = centered_rect(60, 60, area);
     frame.render_widget(Clear, popup);
 
-    let count = app.confirm_delete_ids.len();
+    let count = app.bulk_action_ids.len();
     let mut lines = Vec::new();
     lines.push(Line::from(format!(
-        "Delete {} message{}?",
+        "Tags for {} message{}:",
         count,
         if count == 1 { "" } else { "s" }
     )));
     lines.push(Line::from(""));
+
+    for (idx, keyword) in app.tag_choices().iter().enumerate() {
+        let tagged = app.tag_count(keyword);
+        let mark = if tagged == 0 {
+            "[ ]"
+        } else if tagged == count {
+            "[x]"
+        } else {
+            "[-]"
+        };
+        let style = if idx == app.tag_index {
+            app.ui_theme.overlay_select
+        } else {
+            Style::default()
+        };
+        let label = keyword_label(keyword);
+        let mut spans = vec![
+            Span::styled(format!("  {} ", mark), style),
+            Span::styled(label.clone(), style.fg(keyword_color(keyword))),
+        ];
+        if label != *keyword {
+            spans.push(Span::styled(format!(" ({})", keyword), style));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    lines.push(Line::from(""));
+    if app.gmail_labels {
+        lines.push(Line::from(format!(
+            "New labels (comma-separated): {}_",
+            app.tag_input
+        )));
+    } else {
+        lines.push(Line::from(format!("New tag: {}_", app.tag_input)));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from("Up/Down select  Enter toggle or add  Esc close"));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("TAGS")
+        .style(app.ui_theme.base)
+        .border_style(app.ui_theme.border);
+    let paragraph = Paragraph::new(Text::from(lines))
+        .style(app.ui_theme.base)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, popup);
+}
+
+pub(crate) fn render_confirm_delete_overlay(frame: &mut ratatui::Frame, area: Rect, app: &App) {
+    let popup = centered_rect(50, 30, area);
+    frame.render_widget(Clear, popup);
+
+    let count = app.confirm_delete_ids.len();
+    let mut lines = Vec::new();
+    let plural = if count == 1 { "" } else { "s" };
+    lines.push(Line::from(if app.delete_moves_to_trash() {
+        format!("Move {} message{} to Trash?", count, plural)
+    } else {
+        format!("Permanently delete {} message{}?", count, plural)
+    }));
+    lines.push(Line::from(""));
     lines.push(Line::from("y confirm"));
     lines.push(Line::from("n cancel"));
     lines.push(Line::from("Esc close"));
@@ -370,6 +442,42 @@ pub(crate) fn render_confirm_draft_overlay(frame: &mut ratatui::Frame, area: Rec
     frame.render_widget(paragraph, popup);
 }
 
+pub(crate) fn render_trust_certificate_overlay(frame: &mut ratatui::Frame, area: Rect, app: &App) {
+    let Some(certificate) = app.confirm_certificate.as_ref() else {
+        return;
+    };
+    let popup = centered_rect(70, 45, area);
+    frame.render_widget(Clear, popup);
+
+    let mut lines = Vec::new();
+    lines.push(Line::from(format!(
+        "The certificate of {}:{} could not be verified:",
+        certificate.host, certificate.port
+    )));
+    lines.push(Line::from(certificate.reason.clone()));
+    lines.push(Line::from(""));
+    lines.push(Line::from("SHA-256 fingerprint:"));
+    lines.push(Line::from(certificate.fingerprint.clone()));
+    lines.push(Line::from(""));
+    lines.push(Line::from(
+        "Only trust it if the fingerprint matches the one your server shows.",
+    ));
+    lines.push(Line::from(""));
+    lines.push(Line::from("y trust and remember"));
+    lines.push(Line::from("n / Esc reject"));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("UNTRUSTED CERTIFICATE")
+        .style(app.ui_theme.base)
+        .border_style(app.ui_theme.border);
+    let paragraph = Paragraph::new(Text::from(lines))
+        .style(app.ui_theme.base)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, popup);
+}
+
 pub(crate) fn render_confirm_compose_attachments_overlay(
     frame: &mut ratatui::Frame,
     area: Rect,
@@ -751,6 +859,8 @@ pub(crate) fn render_help_overlay(frame: &mut ratatui::Frame, area: Rect, app: &
             Line::from("  c               compose new"),
             Line::from("  m               move to folder"),
             Line::from("  d               delete"),
+            Line::from("  F               toggle flag"),
+            Line::from("  t               edit tags"),
             Line::from(""),
             Line::from(Span::styled("Other", bold)),
             Line::from("  s               sync folder"),
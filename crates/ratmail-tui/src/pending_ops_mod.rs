@@ -0,0 +1,498 @@
+use std::time::{Duration, Instant};
+
+use ratmail_core::{PendingOp, SqliteMailStore, log_debug};
+use ratmail_mail::{ImapErrorKind, MailCommand, MailEngine};
+
+use super::{App, StoreUpdate, truncate_label};
+
+/// First wait before replaying after a failure; doubles with each failure.
+const PENDING_RETRY_BASE_SECS: u64 = 30;
+const PENDING_RETRY_MAX_SECS: u64 = 60 * 60;
+
+/// Stores a server change in `pending_ops`, then hands it to the engine as
+/// `MailCommand::Pending`. Fails only if the command could not be stored.
+pub(crate) async fn queue_mail_command(
+    store: &SqliteMailStore,
+    account_id: i64,
+    engine: &MailEngine,
+    command: MailCommand,
+) -> anyhow::Result<i64> {
+    let kind = command
+        .pending_kind()
+        .ok_or_else(|| anyhow::anyhow!("command does not change the server"))?;
+    let payload = serde_json::to_string(&command)?;
+    let op_id = store.enqueue_pending_op(account_id, kind, &payload).await?;
+    if let Err(err) = engine.send(MailCommand::Pending {
+        op_id,
+        command: Box::new(command),
+    }) {
+        // Counted as a failed attempt, so the replay picks it up.
+        if let Err(err) = store
+            .record_pending_op_failure(op_id, &err.to_string(), false)
+            .await
+        {
+            log_debug(&format!("pending op {} not updated: {}", op_id, err));
+        }
+    }
+    Ok(op_id)
+}
+
+/// Short description of a queued operation for the status bar and the CLI.
+pub(crate) fn pending_op_label(op: &PendingOp) -> String {
+    let Ok(command) = serde_json::from_str::<MailCommand>(&op.payload) else {
+        return format!("{} (unreadable)", op.kind);
+    };
+    match command {
+        MailCommand::SetFlag {
+            folder_name,
+            uid,
+            seen,
+            ..
+        } => format!(
+            "set-flag {} {} uid={}",
+            if seen { "seen" } else { "unseen" },
+            folder_name,
+            uid
+        ),
+        MailCommand::SetFlagged {
+            folder_name,
+            uid,
+            flagged,
+            ..
+        } => format!(
+            "set-flagged {} {} uid={}",
+            if flagged { "on" } else { "off" },
+            folder_name,
+            uid
+        ),
+        MailCommand::SetKeywords {
+            folder_name,
+            uid,
+            add,
+            remove,
+            ..
+        } => {
+            let changes: Vec<String> = add
+                .iter()
+                .map(|k| format!("+{}", k))
+                .chain(remove.iter().map(|k| format!("-{}", k)))
+                .collect();
+            format!(
+                "set-keywords {} {} uid={}",
+                changes.join(" "),
+                folder_name,
+                uid
+            )
+        }
+        MailCommand::MoveMessages {
+            folder_name,
+            target_folder,
+            uids,
+        } => format!("move {} -> {} ({})", folder_name, target_folder, uids.len()),
+        MailCommand::DeleteMessages {
+            folder_name, uids, ..
+        } => format!("delete {} ({})", folder_name, uids.len()),
+        MailCommand::SendMessage { to, subject, .. } => {
+            format!("send \"{}\" to {}", truncate_label(&subject, 40), to)
+        }
+        MailCommand::SaveDraft { folder_name, .. } => format!("save-draft {}", folder_name),
+        _ => op.kind.clone(),
+    }
+}
+
+/// Whether a failure parks the op instead of retrying it. Failures without a
+/// kind never reached the server.
+pub(crate) fn pending_op_parks(kind: Option<ImapErrorKind>) -> bool {
+    kind.is_some_and(ImapErrorKind::is_permanent)
+}
+
+/// The source folder and UIDs a queued move, or a delete into the trash,
+/// took out of that folder locally. They go back there if the server refuses
+/// the op or it is dropped from the queue.
+pub(crate) fn moved_out_of(command: &MailCommand) -> Option<(&str, &[u32])> {
+    match command {
+        MailCommand::MoveMessages {
+            folder_name, uids, ..
+        }
+        | MailCommand::DeleteMessages {
+            folder_name,
+            uids,
+            trash_folder: Some(_),
+        } => Some((folder_name, uids)),
+        _ => None,
+    }
+}
+
+/// Replay state of the `pending_ops` queue. Operations that failed, and those
+/// left over from an earlier run, are replayed one at a time in queue order.
+/// Operations the server refused are parked and skipped.
+#[derive(Default)]
+pub(crate) struct PendingOps {
+    /// Highest op id stored before this run; those are replayed even if
+    /// they were never attempted.
+    replay_floor: i64,
+    /// Op currently replayed; the next one waits for its answer.
+    replaying: Option<i64>,
+    queued: usize,
+    retrying: usize,
+    parked: usize,
+    failures: u32,
+    /// When to replay next; `None` while nothing needs replaying.
+    retry_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl PendingOps {
+    fn update_counts(&mut self, ops: &[PendingOp]) {
+        self.parked = ops.iter().filter(|op| op.failed).count();
+        self.queued = ops.len() - self.parked;
+        self.retrying = ops
+            .iter()
+            .filter(|op| !op.failed && op.attempts > 0)
+            .count();
+    }
+
+    /// The oldest op to replay, if any.
+    fn next_replay<'a>(&self, ops: &'a [PendingOp]) -> Option<&'a PendingOp> {
+        ops.iter()
+            .filter(|op| !op.failed)
+            .find(|op| op.id <= self.replay_floor || op.attempts > 0)
+    }
+
+    fn finished(
+        &mut self,
+        op_id: i64,
+        error: Option<&str>,
+        kind: Option<ImapErrorKind>,
+        now: Instant,
+    ) {
+        if self.replaying == Some(op_id) {
+            self.replaying = None;
+        }
+        match error.filter(|_| !pending_op_parks(kind)) {
+            // Success, or a refused op that is now parked: the server
+            // answered, so carry on with the backlog.
+            None => {
+                self.failures = 0;
+                self.last_error = None;
+                self.retry_at = Some(now);
+            }
+            Some(reason) => {
+                self.failures = self.failures.saturating_add(1);
+                self.last_error = Some(reason.to_string());
+                let wait = Duration::from_secs(PENDING_RETRY_BASE_SECS)
+                    .saturating_mul(1 << (self.failures - 1).min(7))
+                    .min(Duration::from_secs(PENDING_RETRY_MAX_SECS));
+                self.retry_at = Some(now + wait);
+            }
+        }
+    }
+
+    /// Status bar text; `None` while the queue is empty.
+    pub(crate) fn status_label(&self, now: Instant) -> Option<String> {
+        if self.queued == 0 && self.parked == 0 {
+            return None;
+        }
+        let parked = if self.parked > 0 {
+            format!(", {} failed", self.parked)
+        } else {
+            String::new()
+        };
+        if self.replaying.is_some() {
+            return Some(format!("queue: {}{} (replaying)", self.queued, parked));
+        }
+        match (&self.last_error, self.retry_at) {
+            (Some(reason), Some(at)) if self.retrying > 0 => Some(format!(
+                "queue: {}{}, retry in {}s ({})",
+                self.queued,
+                parked,
+                at.saturating_duration_since(now).as_secs(),
+                truncate_label(reason, 40)
+            )),
+            _ => Some(format!("queue: {}{}", self.queued, parked)),
+        }
+    }
+}
+
+impl App {
+    /// Picks up operations a previous run left in the queue.
+    pub(crate) fn load_pending_ops(&mut self) {
+        let account_id = self.store.account.id;
+        let ops = match self
+            .runtime()
+            .block_on(self.store_handle.list_pending_ops(account_id))
+        {
+            Ok(ops) => ops,
+            Err(err) => {
+                log_debug(&format!("pending ops not loaded: {}", err));
+                return;
+            }
+        };
+        self.pending_ops.replay_floor = ops.last().map(|op| op.id).unwrap_or(0);
+        self.pending_ops.update_counts(&ops);
+        if !ops.is_empty() {
+            self.pending_ops.retry_at = Some(Instant::now());
+        }
+    }
+
+    /// Stores `command` in the queue and sends it, so it is retried after a
+    /// failure or a restart.
+    pub(crate) fn queue_command(&mut self, command: MailCommand) -> anyhow::Result<i64> {
+        let account_id = self.store.account.id;
+        let op_id = self.runtime().block_on(queue_mail_command(
+            &self.store_handle,
+            account_id,
+            &self.engine,
+            command,
+        ))?;
+        self.pending_ops.queued += 1;
+        Ok(op_id)
+    }
+
+    /// Like `queue_command`, but still sends the command when the queue
+    /// cannot be written.
+    pub(crate) fn send_mail_command(&mut self, command: MailCommand) {
+        if let Err(err) = self.queue_command(command.clone()) {
+            log_debug(&format!("mail command not queued: {}", err));
+            let _ = self.engine.send(command);
+        }
+    }
+
+    /// Replays the next queued operation once its retry time has come;
+    /// called from `on_tick`.
+    pub(crate) fn replay_pending_ops(&mut self) {
+        let now = Instant::now();
+        if self.pending_ops.replaying.is_some()
+            || self.sync_poll.auth_failed
+            || self.pending_ops.retry_at.is_none_or(|at| at > now)
+        {
+            return;
+        }
+        let account_id = self.store.account.id;
+        let ops = self
+            .runtime()
+            .block_on(self.store_handle.list_pending_ops(account_id))
+            .unwrap_or_default();
+        self.pending_ops.update_counts(&ops);
+        let Some(op) = self.pending_ops.next_replay(&ops) else {
+            self.pending_ops.retry_at = None;
+            return;
+        };
+        let command = match serde_json::from_str::<MailCommand>(&op.payload) {
+            Ok(command) => command,
+            Err(err) => {
+                // Left for `ratmail queue drop`; it would block the rest.
+                log_debug(&format!("pending op {} unreadable: {}", op.id, err));
+                self.pending_ops.retry_at = None;
+                self.pending_ops.last_error = Some(format!("op {} unreadable", op.id));
+                return;
+            }
+        };
+        log_debug(&format!(
+            "pending op replay id={} attempts={} {}",
+            op.id,
+            op.attempts,
+            pending_op_label(op)
+        ));
+        let op_id = op.id;
+        match self.engine.send(MailCommand::Pending {
+            op_id,
+            command: Box::new(command),
+        }) {
+            Ok(()) => self.pending_ops.replaying = Some(op_id),
+            Err(err) => self
+                .pending_ops
+                .finished(op_id, Some(&err.to_string()), None, now),
+        }
+    }
+
+    /// A successful sync means the server is reachable; retry failed
+    /// operations without waiting for their backoff.
+    pub(crate) fn retry_pending_ops_now(&mut self) {
+        if self.pending_ops.retrying > 0 && self.pending_ops.replaying.is_none() {
+            self.pending_ops.retry_at = Some(Instant::now());
+        }
+    }
+
+    pub(crate) fn on_pending_op_finished(
+        &mut self,
+        op_id: i64,
+        error: Option<String>,
+        kind: Option<ImapErrorKind>,
+    ) {
+        let parked = error.is_some() && pending_op_parks(kind);
+        let op = self
+            .runtime()
+            .block_on(self.store_handle.get_pending_op(op_id))
+            .unwrap_or_default();
+        let stored = match &error {
+            None => self
+                .runtime()
+                .block_on(self.store_handle.delete_pending_op(op_id))
+                .map(|_| ()),
+            Some(reason) => self.runtime().block_on(
+                self.store_handle
+                    .record_pending_op_failure(op_id, reason, parked),
+            ),
+        };
+        if let Err(err) = stored {
+            log_debug(&format!("pending op {} not updated: {}", op_id, err));
+        }
+        self.pending_ops
+            .finished(op_id, error.as_deref(), kind, Instant::now());
+        let account_id = self.store.account.id;
+        if let Ok(ops) = self
+            .runtime()
+            .block_on(self.store_handle.list_pending_ops(account_id))
+        {
+            self.pending_ops.update_counts(&ops);
+        }
+        // A send kept its message as a draft until it went out.
+        if error.is_none()
+            && let Some(message_id) = op.as_ref().and_then(|op| op.draft_id)
+        {
+            self.queue_store_update_reliable(StoreUpdate::DiscardDraft {
+                account_id,
+                message_id,
+            });
+        }
+        if parked
+            && let Some(command) = op
+                .as_ref()
+                .and_then(|op| serde_json::from_str::<MailCommand>(&op.payload).ok())
+            && let Some((source_folder, uids)) = moved_out_of(&command)
+        {
+            let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+            self.queue_store_update_reliable(StoreUpdate::RestoreMovedMessages {
+                account_id,
+                source_folder: source_folder.to_string(),
+                uids: uids.to_vec(),
+                refresh_folder_id,
+            });
+        }
+        if self.sending_op == Some(op_id) {
+            self.sending_op = None;
+            self.finish_send(op.as_ref(), error.as_deref(), parked);
+        } else if parked && let Some(op) = &op {
+            self.set_status(format!(
+                "Server refused {}: {}; kept as failed in the queue",
+                pending_op_label(op),
+                truncate_label(error.as_deref().unwrap_or_default(), 60)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use ratmail_core::PendingOp;
+    use ratmail_mail::ImapErrorKind;
+
+    use super::{PendingOps, pending_op_parks};
+
+    fn op(id: i64, attempts: i64) -> PendingOp {
+        PendingOp {
+            id,
+            account_id: 1,
+            kind: "set_flag".to_string(),
+            payload: "{}".to_string(),
+            created_ts: 0,
+            attempts,
+            last_error: None,
+            last_attempt_ts: None,
+            failed: false,
+            draft_id: None,
+        }
+    }
+
+    #[test]
+    fn pending_ops_replay_in_order_and_back_off() {
+        let mut pending = PendingOps {
+            replay_floor: 2,
+            ..PendingOps::default()
+        };
+        // Ops from this run are only replayed once they failed.
+        let ops = vec![op(3, 0), op(4, 1), op(5, 0)];
+        assert_eq!(pending.next_replay(&ops).map(|op| op.id), Some(4));
+        let ops = vec![op(2, 0), op(4, 1)];
+        assert_eq!(pending.next_replay(&ops).map(|op| op.id), Some(2));
+        pending.update_counts(&ops);
+        assert_eq!((pending.queued, pending.retrying), (2, 1));
+
+        let start = Instant::now();
+        pending.replaying = Some(2);
+        pending.finished(
+            2,
+            Some("connection refused"),
+            Some(ImapErrorKind::Network),
+            start,
+        );
+        assert_eq!(pending.replaying, None);
+        assert_eq!(pending.retry_at, Some(start + Duration::from_secs(30)));
+        pending.finished(
+            4,
+            Some("connection refused"),
+            Some(ImapErrorKind::Network),
+            start,
+        );
+        assert_eq!(pending.retry_at, Some(start + Duration::from_secs(60)));
+        assert_eq!(
+            pending.status_label(start).as_deref(),
+            Some("queue: 2, retry in 60s (connection refused)")
+        );
+        for _ in 0..10 {
+            pending.finished(
+                4,
+                Some("connection refused"),
+                Some(ImapErrorKind::Network),
+                start,
+            );
+        }
+        assert_eq!(pending.retry_at, Some(start + Duration::from_secs(3600)));
+
+        pending.finished(4, None, None, start);
+        assert_eq!(pending.retry_at, Some(start));
+        assert_eq!(pending.failures, 0);
+    }
+
+    #[test]
+    fn refused_pending_op_is_parked_without_blocking_the_queue() {
+        let mut pending = PendingOps::default();
+        let start = Instant::now();
+        pending.replaying = Some(4);
+        pending.finished(
+            4,
+            Some("NO [NONEXISTENT] Unknown mailbox"),
+            Some(ImapErrorKind::MailboxMissing),
+            start,
+        );
+        // No backoff: the next op goes out right away.
+        assert_eq!(pending.retry_at, Some(start));
+        assert_eq!(pending.failures, 0);
+
+        let mut refused = op(4, 1);
+        refused.failed = true;
+        let ops = vec![refused, op(5, 1)];
+        assert_eq!(pending.next_replay(&ops).map(|op| op.id), Some(5));
+        pending.update_counts(&ops);
+        assert_eq!(
+            pending.status_label(start).as_deref(),
+            Some("queue: 1, 1 failed")
+        );
+
+        // Errors that never reached the server are retried, not parked.
+        pending.finished(5, Some("mail command queue full"), None, start);
+        assert_eq!(pending.retry_at, Some(start + Duration::from_secs(30)));
+        pending.finished(5, Some("535 auth"), Some(ImapErrorKind::AuthFailed), start);
+        assert_eq!(pending.retry_at, Some(start + Duration::from_secs(60)));
+
+        // A NO without a refusal code may pass later: it is retried too.
+        let server_bug = "No Response: [SERVERBUG] Internal error";
+        let kind = ImapErrorKind::classify(&anyhow::anyhow!(server_bug));
+        assert!(!pending_op_parks(Some(kind)));
+        pending.finished(5, Some(server_bug), Some(kind), start);
+        assert_eq!(pending.retry_at, Some(start + Duration::from_secs(120)));
+    }
+}
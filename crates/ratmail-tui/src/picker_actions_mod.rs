@@ -460,6 +460,10 @@ impl App {
         let raw = self.get_raw_body_or_fetch(message_id)?;
         let data = extract_attachment_data(&raw, attachment_index)?
             .ok_or_else(|| anyhow::anyhow!("attachment not found"))?;
+        if let Some(section) = data.omitted_section {
+            self.fetch_body_part(message_id, section);
+            return Err(anyhow::anyhow!("message body not cached"));
+        }
 
         let mut target = PathBuf::from(path);
         if target.is_dir() {
@@ -499,6 +503,34 @@ impl App {
         Err(anyhow::anyhow!("message body not cached"))
     }
 
+    /// Downloads an attachment left out of a partially fetched body; the
+    /// user retries the action once `ImapBodyPart` has been stored.
+    pub(crate) fn fetch_body_part(&mut self, message_id: i64, section: String) {
+        if !self.imap_enabled {
+            self.set_status("Attachment not downloaded");
+            return;
+        }
+        if !self
+            .pending_part_fetch
+            .insert((message_id, section.clone()))
+        {
+            self.set_status("Downloading attachment...");
+            return;
+        }
+        let (uid, folder_name) = self.message_location(message_id);
+        let (Some(uid), Some(folder_name)) = (uid, folder_name) else {
+            self.pending_part_fetch.remove(&(message_id, section));
+            return;
+        };
+        let _ = self.engine.send(MailCommand::FetchMessagePart {
+            message_id,
+            folder_name,
+            uid,
+            section,
+        });
+        self.set_status("Downloading attachment...");
+    }
+
     pub(crate) fn message_location(&self, message_id: i64) -> (Option<u32>, Option<String>) {
         let message = self.store.messages.iter().find(|m| m.id == message_id);
         let uid = message.and_then(|m| m.imap_uid);
@@ -1,15 +1,145 @@
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 
 use ratmail_content::{extract_attachments, extract_display};
-use ratmail_core::{DEFAULT_TEXT_WIDTH, Folder, MessageDetail, MessageSummary, log_debug};
-use ratmail_mail::{ImapErrorContext, MailCommand, MailEvent};
+use ratmail_core::{
+    DEFAULT_TEXT_WIDTH, Folder, MessageDetail, MessageSummary, PendingOp, log_debug,
+};
+use ratmail_mail::{
+    ImapConfig, ImapErrorContext, ImapErrorKind, ImapMailboxState, MailCommand, MailEvent,
+    UntrustedCertificate, trust_certificate,
+};
 
 use super::{
-    App, ComposeFocus, Focus, Mode, StoreUpdate, ViewMode, build_sync_update, cc_from_raw,
-    compose_buffer_from_body, to_from_raw,
+    App, ComposeFocus, Focus, Mode, SERVER_SEARCH_LIMIT, ServerSearch, StoreUpdate, ViewMode,
+    build_sync_update, cc_from_raw, compose_buffer_from_body, message_summary_from_imap,
+    sync_resume_modes, to_from_raw,
 };
 
+/// Longest wait between background syncs of a folder that keeps failing.
+const POLL_BACKOFF_MAX_SECS: u64 = 60 * 60;
+
+/// Background incremental syncs (`poll_interval_secs`). Only folders that
+/// synced at least once are polled, each on its own timer.
+#[derive(Default)]
+pub(crate) struct SyncPoll {
+    interval: Option<Duration>,
+    /// Per-folder overrides; `None` turns polling off for that folder.
+    folder_intervals: HashMap<String, Option<Duration>>,
+    folders: HashMap<String, FolderPoll>,
+    /// A `SyncAll` is walking the folders; polling waits for it.
+    sync_all_running: bool,
+    /// The server rejected the login. Background syncs and queue replays
+    /// wait until a sync the user starts succeeds.
+    pub(crate) auth_failed: bool,
+}
+
+struct FolderPoll {
+    next_due: Instant,
+    failures: u32,
+    pending: bool,
+}
+
+impl SyncPoll {
+    /// Folders already watched with IDLE are not polled unless they have an
+    /// explicit interval.
+    pub(crate) fn from_config(imap: Option<&ImapConfig>) -> Self {
+        let Some(imap) = imap else {
+            return Self::default();
+        };
+        let interval = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        let mut folder_intervals: HashMap<String, Option<Duration>> = imap
+            .idle_folders
+            .iter()
+            .map(|name| (name.clone(), None))
+            .collect();
+        for (name, secs) in &imap.folder_poll_secs {
+            folder_intervals.insert(name.clone(), interval(*secs));
+        }
+        Self {
+            interval: interval(imap.poll_interval_secs),
+            folder_intervals,
+            ..Self::default()
+        }
+    }
+
+    fn interval_for(&self, folder_name: &str) -> Option<Duration> {
+        match self.folder_intervals.get(folder_name) {
+            Some(interval) => *interval,
+            None => self.interval,
+        }
+    }
+
+    /// Folders whose timer ran out and that have no sync in flight.
+    fn due(&self, now: Instant) -> Vec<String> {
+        if self.sync_all_running || self.auth_failed {
+            return Vec::new();
+        }
+        self.folders
+            .iter()
+            .filter(|(_, poll)| !poll.pending && poll.next_due <= now)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn started(&mut self, folder_name: &str) {
+        if let Some(poll) = self.folders.get_mut(folder_name) {
+            poll.pending = true;
+        }
+    }
+
+    /// Re-arms the folder's timer, doubling the wait after each failure.
+    fn finished(&mut self, folder_name: &str, ok: bool, now: Instant) {
+        if ok {
+            self.auth_failed = false;
+        }
+        let Some(interval) = self.interval_for(folder_name) else {
+            return;
+        };
+        let poll = self
+            .folders
+            .entry(folder_name.to_string())
+            .or_insert(FolderPoll {
+                next_due: now,
+                failures: 0,
+                pending: false,
+            });
+        poll.pending = false;
+        poll.failures = if ok {
+            0
+        } else {
+            poll.failures.saturating_add(1)
+        };
+        let wait = interval
+            .saturating_mul(1 << poll.failures.min(6))
+            .min(Duration::from_secs(POLL_BACKOFF_MAX_SECS).max(interval));
+        poll.next_due = now + wait;
+    }
+}
+
 impl App {
+    /// Starts the background syncs that are due; called from `on_tick`.
+    pub(crate) fn poll_folders(&mut self) {
+        if !self.imap_enabled {
+            return;
+        }
+        for folder_name in self.sync_poll.due(Instant::now()) {
+            let Some(folder_id) = self
+                .store
+                .folders
+                .iter()
+                .find(|f| f.name == folder_name)
+                .map(|f| f.id)
+            else {
+                self.sync_poll.folders.remove(&folder_name);
+                continue;
+            };
+            log_debug(&format!("imap_poll folder={}", folder_name));
+            self.sync_poll.started(&folder_name);
+            self.request_sync_folder(folder_id, folder_name);
+        }
+    }
+
     pub(crate) fn request_sync_selected_folder(&mut self) {
         if !self.imap_enabled {
             return;
@@ -26,21 +156,101 @@ impl App {
             }
         }
         self.last_folder_sync = Some((folder_name.clone(), now));
+        self.request_sync_folder(folder_id, folder_name);
+    }
+
+    /// Lists folders and syncs all of them, resuming cached ones where the
+    /// last sync stopped.
+    pub(crate) fn request_sync_all(&mut self) -> bool {
+        let resume = sync_resume_modes(self.runtime(), &self.store_handle, &self.store.folders);
+        if self.engine.send(MailCommand::SyncAll { resume }).is_err() {
+            return false;
+        }
         self.imap_pending = self.imap_pending.saturating_add(1);
         self.imap_status = Some("IMAP syncing...".to_string());
-        let last_seen_uid = self.runtime().block_on(async {
+        true
+    }
+
+    /// Incremental sync for a folder the server told us changed.
+    pub(crate) fn request_sync_folder_by_name(&mut self, folder_name: &str) {
+        if !self.imap_enabled {
+            return;
+        }
+        let Some(folder_id) = self
+            .store
+            .folders
+            .iter()
+            .find(|f| f.name == folder_name)
+            .map(|f| f.id)
+        else {
+            return;
+        };
+        self.request_sync_folder(folder_id, folder_name.to_string());
+    }
+
+    /// `initial_sync_days` for `folder_name`, with its override if any.
+    fn sync_days_for(&self, folder_name: &str) -> i64 {
+        self.folder_sync_days
+            .get(folder_name)
+            .copied()
+            .unwrap_or(self.initial_sync_days)
+    }
+
+    fn request_initial_sync_folder(&mut self, folder_name: String) {
+        self.imap_pending = self.imap_pending.saturating_add(1);
+        let days = self.sync_days_for(&folder_name);
+        let _ = self.engine.send(MailCommand::SyncFolderByName {
+            name: folder_name,
+            mode: ratmail_mail::SyncMode::Initial { days },
+        });
+    }
+
+    /// True when the server reports a different UIDVALIDITY than the one the
+    /// folder was cached under, i.e. every cached UID is now meaningless.
+    fn folder_uidvalidity_changed(&self, folder_name: &str, mailbox: ImapMailboxState) -> bool {
+        let Some(uidvalidity) = mailbox.uidvalidity else {
+            return false;
+        };
+        let Some(folder_id) = self
+            .store
+            .folders
+            .iter()
+            .find(|f| f.name == folder_name)
+            .map(|f| f.id)
+        else {
+            return false;
+        };
+        let cached = self.runtime().block_on(async {
             self.store_handle
                 .get_folder_sync_state(folder_id)
                 .await
                 .ok()
                 .flatten()
-                .and_then(|s| s.last_seen_uid)
-                .map(|v| v as u32)
+                .and_then(|s| s.uidvalidity)
         });
-        let mode = match last_seen_uid {
-            Some(uid) => ratmail_mail::SyncMode::Incremental { last_seen_uid: uid },
+        cached.is_some_and(|cached| cached != i64::from(uidvalidity))
+    }
+
+    fn request_sync_folder(&mut self, folder_id: i64, folder_name: String) {
+        self.imap_pending = self.imap_pending.saturating_add(1);
+        self.imap_status = Some("IMAP syncing...".to_string());
+        let state = self.runtime().block_on(async {
+            self.store_handle
+                .get_folder_sync_state(folder_id)
+                .await
+                .ok()
+                .flatten()
+        });
+        let mode = match state.as_ref().and_then(|s| s.last_seen_uid) {
+            Some(uid) => ratmail_mail::SyncMode::Incremental {
+                last_seen_uid: uid as u32,
+                highest_modseq: state
+                    .as_ref()
+                    .and_then(|s| s.highest_modseq)
+                    .map(|v| v as u64),
+            },
             None => ratmail_mail::SyncMode::Initial {
-                days: self.initial_sync_days,
+                days: self.sync_days_for(&folder_name),
             },
         };
         let _ = self.engine.send(MailCommand::SyncFolderByName {
@@ -81,7 +291,7 @@ impl App {
         self.imap_status = Some("IMAP loading older...".to_string());
         let mode = ratmail_mail::SyncMode::Backfill {
             before_ts,
-            window_days: self.initial_sync_days,
+            window_days: self.sync_days_for(&folder_name),
         };
         let _ = self.engine.send(MailCommand::SyncFolderByName {
             name: folder_name,
@@ -89,8 +299,167 @@ impl App {
         });
     }
 
+    /// Asks the server to run the current search in the selected folder, so
+    /// matches older than the synced window show up too.
+    pub(crate) fn request_server_search(&mut self) {
+        if !self.imap_enabled {
+            return;
+        }
+        let Some(query) = self.search_spec.imap_query() else {
+            return;
+        };
+        let Some(folder) = self.selected_folder() else {
+            return;
+        };
+        let folder_id = folder.id;
+        let folder_name = folder.name.clone();
+        if self
+            .server_search
+            .as_ref()
+            .is_some_and(|s| s.folder_id == folder_id && s.query == query)
+        {
+            return;
+        }
+        if self
+            .engine
+            .send(MailCommand::SearchFolder {
+                folder_name: folder_name.clone(),
+                query: query.clone(),
+                limit: SERVER_SEARCH_LIMIT,
+            })
+            .is_err()
+        {
+            return;
+        }
+        self.imap_status = Some(format!("IMAP: searching {} on the server...", folder_name));
+        self.server_search = Some(ServerSearch {
+            folder_id,
+            folder_name,
+            query,
+            uids: HashSet::new(),
+            pending: true,
+        });
+    }
+
+    /// Asks whether to pin a certificate that failed validation.
+    fn prompt_certificate(&mut self, certificate: UntrustedCertificate) {
+        if self.mode != Mode::OverlayTrustCertificate {
+            self.confirm_certificate_return = self.mode;
+        }
+        self.confirm_certificate = Some(certificate);
+        self.mode = Mode::OverlayTrustCertificate;
+    }
+
+    pub(crate) fn trust_confirmed_certificate(&mut self) {
+        self.mode = self.confirm_certificate_return;
+        let Some(certificate) = self.confirm_certificate.take() else {
+            return;
+        };
+        if let Err(err) = trust_certificate(
+            &certificate.host,
+            certificate.port,
+            &certificate.fingerprint,
+        ) {
+            self.set_status(format!("Could not save certificate: {}", err));
+            return;
+        }
+        self.set_status(format!(
+            "Trusted certificate of {}:{}",
+            certificate.host, certificate.port
+        ));
+        if self.imap_enabled {
+            self.request_sync_all();
+        }
+    }
+
+    /// The message sent from compose went out, or failed. A failed message
+    /// stays in the queue and is kept in Drafts until it goes out; `parked`
+    /// means the server refused it and it is not retried on its own.
+    pub(crate) fn finish_send(
+        &mut self,
+        op: Option<&PendingOp>,
+        error: Option<&str>,
+        parked: bool,
+    ) {
+        let account_id = self.store.account.id;
+        match error {
+            None => {
+                if let Some(message_id) = self.sending_draft_id.take() {
+                    self.queue_store_update_reliable(StoreUpdate::DiscardDraft {
+                        account_id,
+                        message_id,
+                    });
+                }
+            }
+            Some(reason) => {
+                let replaces = self.sending_draft_id.take();
+                let command = op.and_then(|op| serde_json::from_str(&op.payload).ok());
+                if let Some(MailCommand::SendMessage {
+                    from,
+                    to,
+                    cc,
+                    bcc,
+                    subject,
+                    body,
+                    ..
+                }) = command
+                {
+                    self.queue_store_update_reliable(StoreUpdate::SaveDraft {
+                        account_id,
+                        from_addr: from.unwrap_or_default(),
+                        to,
+                        cc,
+                        bcc,
+                        subject,
+                        body,
+                        replaces,
+                        send_op: op.map(|op| op.id),
+                    });
+                }
+                if parked {
+                    self.set_status(format!(
+                        "Send failed: {}; kept in Drafts and as failed in the queue",
+                        reason
+                    ));
+                } else {
+                    self.set_status(format!(
+                        "Send failed: {}; queued for retry, kept in Drafts",
+                        reason
+                    ));
+                }
+            }
+        }
+        if self.mode == Mode::Compose {
+            self.mode = Mode::List;
+            self.focus = Focus::Messages;
+            self.compose_to.clear();
+            self.compose_cc.clear();
+            self.compose_bcc.clear();
+            self.compose_subject.clear();
+            self.compose_body = compose_buffer_from_body(self.ui_theme.clone(), "");
+            self.compose_quote.clear();
+            self.compose_attachments.clear();
+            self.compose_pending_parts.clear();
+            self.compose_draft_id = None;
+            self.compose_focus = ComposeFocus::Body;
+            self.compose_cursor_to = 0;
+            self.compose_cursor_cc = 0;
+            self.compose_cursor_bcc = 0;
+            self.compose_cursor_subject = 0;
+            self.compose_body_desired_x = None;
+            self.reset_compose_vim_state();
+        }
+    }
+
     pub(crate) fn on_event(&mut self, event: MailEvent) {
         match event {
+            MailEvent::CertificateUntrusted(certificate) => self.prompt_certificate(certificate),
+            MailEvent::CertificateChanged(mismatch) => {
+                self.set_status(format!(
+                    "Certificate of {}:{} CHANGED; not connecting. Remove its pin if the change is expected.",
+                    mismatch.host, mismatch.port
+                ));
+            }
             MailEvent::SyncStarted(_) => self.sync_status = "syncing".to_string(),
             MailEvent::SyncCompleted(_) => self.sync_status = "idle".to_string(),
             MailEvent::SyncFailed { .. } => self.sync_status = "error".to_string(),
@@ -99,28 +468,62 @@ impl App {
             }
             MailEvent::SendCompleted => {
                 self.set_status("Sent");
-                if self.mode == Mode::Compose {
-                    self.mode = Mode::List;
-                    self.focus = Focus::Messages;
-                    self.compose_to.clear();
-                    self.compose_cc.clear();
-                    self.compose_bcc.clear();
-                    self.compose_subject.clear();
-                    self.compose_body = compose_buffer_from_body(self.ui_theme.clone(), "");
-                    self.compose_quote.clear();
-                    self.compose_attachments.clear();
-                    self.compose_focus = ComposeFocus::Body;
-                    self.compose_cursor_to = 0;
-                    self.compose_cursor_cc = 0;
-                    self.compose_cursor_bcc = 0;
-                    self.compose_cursor_subject = 0;
-                    self.compose_body_desired_x = None;
-                    self.reset_compose_vim_state();
-                }
             }
             MailEvent::SendFailed { reason } => {
                 self.set_status(format!("Send failed: {}", reason));
             }
+            MailEvent::PendingOpFinished { op_id, error, kind } => {
+                self.on_pending_op_finished(op_id, error, kind);
+            }
+            MailEvent::DraftSaved {
+                message_id,
+                folder_name,
+                uid,
+                raw,
+            } => {
+                log_debug(&format!(
+                    "draft uploaded id={} folder={} uid={:?}",
+                    message_id, folder_name, uid
+                ));
+                let account_id = self.store.account.id;
+                self.queue_store_update_reliable(StoreUpdate::DraftUploaded {
+                    account_id,
+                    message_id,
+                    uid,
+                    raw,
+                });
+            }
+            MailEvent::MessagesMoved {
+                folder_name,
+                target_folder,
+                uids,
+            } if !uids.is_empty() => {
+                let account_id = self.store.account.id;
+                let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+                self.queue_store_update_reliable(StoreUpdate::RemapMovedUids {
+                    account_id,
+                    source_folder: folder_name,
+                    folder_name: target_folder,
+                    uids,
+                    refresh_folder_id,
+                });
+            }
+            MailEvent::SentSaved {
+                folder_name,
+                uid,
+                raw,
+            } => {
+                let account_id = self.store.account.id;
+                self.queue_store_update_reliable(StoreUpdate::SaveSent {
+                    account_id,
+                    folder_name,
+                    uid,
+                    raw,
+                });
+            }
+            MailEvent::ImapCapabilities { gmail } => {
+                self.gmail_labels = gmail;
+            }
             MailEvent::ImapFolders(folders) => {
                 self.imap_pending = self.imap_pending.saturating_sub(1);
                 if self.imap_pending == 0 {
@@ -135,6 +538,7 @@ impl App {
                         account_id,
                         name: f.name,
                         unread: f.unread,
+                        role: f.role,
                     })
                     .collect();
                 self.queue_store_update(StoreUpdate::Folders {
@@ -142,9 +546,27 @@ impl App {
                     folders: models,
                 });
             }
+            MailEvent::FolderSyncProgress {
+                folder_name,
+                done,
+                total,
+            } => {
+                // `ImapFolders` settled the `SyncAll` itself; every planned
+                // folder still reports `ImapMessages` or an error.
+                if done == 0 && folder_name.is_some() {
+                    self.imap_pending = self.imap_pending.saturating_add(total);
+                }
+                self.sync_poll.sync_all_running = folder_name.is_some();
+                self.sync_status = match folder_name {
+                    Some(_) => format!("syncing {}/{} folders", done + 1, total),
+                    None => "idle".to_string(),
+                };
+            }
             MailEvent::ImapMessages {
                 folder_name,
+                mailbox,
                 messages,
+                changes,
             } => {
                 self.imap_pending = self.imap_pending.saturating_sub(1);
                 if self.imap_pending == 0 {
@@ -163,27 +585,67 @@ impl App {
                 ));
                 let items: Vec<MessageSummary> = messages
                     .into_iter()
-                    .map(|m| MessageSummary {
-                        id: 0,
-                        folder_id: 0,
-                        imap_uid: Some(m.uid),
-                        date: m.date,
-                        from: m.from,
-                        subject: m.subject,
-                        unread: m.unread,
-                        preview: m.preview,
-                    })
+                    .map(message_summary_from_imap)
                     .collect();
-                let sync_update = build_sync_update(&items);
+                self.sync_poll.finished(&folder_name, true, Instant::now());
+                self.retry_pending_ops_now();
+                let invalidate = self.folder_uidvalidity_changed(&folder_name, mailbox);
+                let sync_update = build_sync_update(&items, mailbox, changes.is_some());
                 self.queue_store_update(StoreUpdate::AppendMessages {
                     account_id,
-                    folder_name,
+                    folder_name: folder_name.clone(),
                     messages: items,
                     sync_update,
+                    invalidate,
+                    // Changes are relative to the old UID numbering; drop them.
+                    changes: changes.filter(|_| !invalidate),
                 });
+                if invalidate {
+                    self.imap_status = Some(format!(
+                        "IMAP: {} was renumbered on the server, resyncing",
+                        folder_name
+                    ));
+                    self.request_initial_sync_folder(folder_name);
+                }
                 self.prefetch_raw_bodies(10);
             }
-            MailEvent::ImapBody { message_id, raw } => {
+            MailEvent::ImapSearchResults {
+                folder_name,
+                query,
+                messages,
+            } => {
+                let Some(search) = self.server_search.as_mut() else {
+                    return;
+                };
+                // Stale answer for a query the user has since changed.
+                if search.folder_name != folder_name || search.query != query {
+                    return;
+                }
+                search.pending = false;
+                search.uids = messages.iter().map(|m| m.uid).collect();
+                let folder_id = search.folder_id;
+                let account_id = self.store.account.id;
+                self.imap_status = Some(format!(
+                    "IMAP: server search found {} in {}",
+                    messages.len(),
+                    folder_name
+                ));
+                let items: Vec<MessageSummary> = messages
+                    .into_iter()
+                    .map(message_summary_from_imap)
+                    .collect();
+                self.queue_store_update(StoreUpdate::SearchResults {
+                    account_id,
+                    folder_id,
+                    messages: items,
+                    refresh_folder_id: folder_id,
+                });
+            }
+            MailEvent::ImapBody {
+                message_id,
+                raw,
+                missing_parts,
+            } => {
                 let account_id = self.store.account.id;
                 self.pending_body_fetch.remove(&message_id);
                 let parsed_to = to_from_raw(&raw);
@@ -238,6 +700,7 @@ impl App {
                     account_id,
                     message_id,
                     raw,
+                    missing_parts,
                     cached_text,
                 });
                 if self.view_mode == ViewMode::Rendered {
@@ -247,19 +710,148 @@ impl App {
                     }
                 }
             }
-            MailEvent::ImapError { context, reason } => {
+            MailEvent::ImapBodyPart {
+                message_id,
+                section,
+                data,
+            } => {
+                self.pending_part_fetch
+                    .remove(&(message_id, section.clone()));
+                self.set_status("Attachment downloaded");
+                self.attach_downloaded_part(message_id, &section, &data);
+                let account_id = self.store.account.id;
+                self.queue_store_update(StoreUpdate::BodyPart {
+                    account_id,
+                    message_id,
+                    section,
+                    data,
+                });
+            }
+            MailEvent::ImapNewMail {
+                folder_name,
+                exists,
+            } => {
+                log_debug(&format!(
+                    "imap_new_mail folder={} exists={}",
+                    folder_name, exists
+                ));
+                self.request_sync_folder_by_name(&folder_name);
+            }
+            MailEvent::ImapExpunged {
+                folder_name,
+                exists,
+            } => {
+                log_debug(&format!(
+                    "imap_expunged folder={} exists={}",
+                    folder_name, exists
+                ));
+                self.request_sync_folder_by_name(&folder_name);
+            }
+            MailEvent::ImapError {
+                context,
+                kind,
+                reason,
+            } => {
                 match context {
-                    ImapErrorContext::SyncAll | ImapErrorContext::SyncFolder { .. } => {
+                    ImapErrorContext::SyncAll => {
                         self.imap_pending = self.imap_pending.saturating_sub(1);
+                        self.sync_poll.sync_all_running = false;
+                    }
+                    ImapErrorContext::SyncFolder {
+                        ref folder_name, ..
+                    } => {
+                        self.imap_pending = self.imap_pending.saturating_sub(1);
+                        self.sync_poll.finished(folder_name, false, Instant::now());
+                        if kind == ImapErrorKind::MailboxMissing {
+                            // Gone on the server; a manual sync brings it back.
+                            self.sync_poll.folders.remove(folder_name);
+                        }
+                    }
+                    ImapErrorContext::SearchFolder { .. } => {
+                        if let Some(search) = self.server_search.as_mut() {
+                            search.pending = false;
+                        }
                     }
                     ImapErrorContext::FetchBody { message_id, .. } => {
                         self.pending_body_fetch.remove(&message_id);
                     }
+                    ImapErrorContext::FetchBodyPart {
+                        message_id,
+                        ref section,
+                        ..
+                    } => {
+                        self.pending_part_fetch
+                            .remove(&(message_id, section.clone()));
+                    }
+                    // Server changes stay queued and keep their local effect;
+                    // `PendingOpFinished` schedules the retry.
+                    ImapErrorContext::SetFlag { .. }
+                    | ImapErrorContext::SetFlagged { .. }
+                    | ImapErrorContext::SetKeywords { .. }
+                        if !kind.is_permanent() => {}
+                    // The server refused the change: undo it locally.
+                    ImapErrorContext::SetFlag {
+                        message_id, seen, ..
+                    } => {
+                        let account_id = self.store.account.id;
+                        let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+                        self.queue_store_update_reliable(StoreUpdate::SetMessagesUnread {
+                            account_id,
+                            message_ids: vec![message_id],
+                            unread: seen,
+                            refresh_folder_id,
+                        });
+                    }
+                    ImapErrorContext::SetFlagged {
+                        message_id,
+                        flagged,
+                        ..
+                    } => {
+                        let account_id = self.store.account.id;
+                        let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+                        self.queue_store_update_reliable(StoreUpdate::SetMessagesFlagged {
+                            account_id,
+                            message_ids: vec![message_id],
+                            flagged: !flagged,
+                            refresh_folder_id,
+                        });
+                    }
+                    ImapErrorContext::SetKeywords {
+                        message_id,
+                        ref add,
+                        ref remove,
+                        ..
+                    } => {
+                        let account_id = self.store.account.id;
+                        let refresh_folder_id = self.selected_folder().map(|f| f.id).unwrap_or(1);
+                        self.queue_store_update_reliable(StoreUpdate::UpdateMessageKeywords {
+                            account_id,
+                            message_ids: vec![message_id],
+                            add: remove.clone(),
+                            remove: add.clone(),
+                            refresh_folder_id,
+                        });
+                    }
                     ImapErrorContext::MoveMessages { .. }
-                    | ImapErrorContext::DeleteMessages { .. } => {}
+                    | ImapErrorContext::DeleteMessages { .. }
+                    | ImapErrorContext::AppendSent { .. }
+                    | ImapErrorContext::SaveDraft { .. } => {}
                 }
                 let context_label = imap_error_context_label(&context);
-                self.imap_status = Some(format!("IMAP error ({}): {}", context_label, reason));
+                self.imap_status = Some(if kind == ImapErrorKind::AuthFailed {
+                    self.sync_poll.auth_failed = true;
+                    format!(
+                        "IMAP login rejected ({}): {}; fix the password and press s to sync",
+                        context_label, reason
+                    )
+                } else {
+                    format!(
+                        "IMAP error ({}, {}): {}",
+                        context_label,
+                        kind.as_str(),
+                        reason
+                    )
+                });
             }
             _ => {}
         }
@@ -280,6 +872,15 @@ fn imap_error_context_label(context: &ImapErrorContext) -> String {
             "fetch-body id={} folder={} uid={}",
             message_id, folder_name, uid
         ),
+        ImapErrorContext::FetchBodyPart {
+            message_id,
+            folder_name,
+            uid,
+            section,
+        } => format!(
+            "fetch-part id={} folder={} uid={} section={}",
+            message_id, folder_name, uid, section
+        ),
         ImapErrorContext::MoveMessages {
             folder_name,
             target_folder,
@@ -291,14 +892,100 @@ fn imap_error_context_label(context: &ImapErrorContext) -> String {
         ImapErrorContext::DeleteMessages { folder_name, count } => {
             format!("delete-messages {} ({})", folder_name, count)
         }
+        ImapErrorContext::SetFlag {
+            message_id,
+            folder_name,
+            uid,
+            seen,
+        } => format!(
+            "set-flag {} id={} folder={} uid={}",
+            if *seen { "seen" } else { "unseen" },
+            message_id,
+            folder_name,
+            uid
+        ),
+        ImapErrorContext::SetFlagged {
+            message_id,
+            folder_name,
+            uid,
+            flagged,
+        } => format!(
+            "set-flag {} id={} folder={} uid={}",
+            if *flagged { "flagged" } else { "unflagged" },
+            message_id,
+            folder_name,
+            uid
+        ),
+        ImapErrorContext::SetKeywords {
+            message_id,
+            folder_name,
+            uid,
+            add,
+            remove,
+        } => format!(
+            "set-keywords +[{}] -[{}] id={} folder={} uid={}",
+            add.join(" "),
+            remove.join(" "),
+            message_id,
+            folder_name,
+            uid
+        ),
+        ImapErrorContext::SearchFolder { folder_name } => format!("search {}", folder_name),
+        ImapErrorContext::AppendSent { folder_name } => {
+            format!("append-sent {}", folder_name)
+        }
+        ImapErrorContext::SaveDraft {
+            message_id,
+            folder_name,
+        } => format!("save-draft id={} folder={}", message_id, folder_name),
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::{Duration, Instant};
+
     use ratmail_mail::ImapErrorContext;
 
-    use crate::sync_mod::imap_error_context_label;
+    use crate::sync_mod::{SyncPoll, imap_error_context_label};
+
+    #[test]
+    fn sync_poll_waits_for_pending_runs_and_backs_off() {
+        let mut poll = SyncPoll {
+            interval: Some(Duration::from_secs(60)),
+            ..SyncPoll::default()
+        };
+        poll.folder_intervals.insert("Spam".to_string(), None);
+        let start = Instant::now();
+        poll.finished("INBOX", true, start);
+        poll.finished("Spam", true, start);
+        assert!(poll.due(start).is_empty());
+
+        let later = start + Duration::from_secs(60);
+        assert_eq!(poll.due(later), vec!["INBOX".to_string()]);
+        poll.started("INBOX");
+        assert!(poll.due(later).is_empty());
+        poll.sync_all_running = true;
+        poll.finished("INBOX", true, start);
+        assert!(poll.due(later).is_empty());
+        poll.sync_all_running = false;
+
+        poll.finished("INBOX", false, start);
+        poll.finished("INBOX", false, start);
+        assert!(poll.due(start + Duration::from_secs(239)).is_empty());
+        assert_eq!(poll.due(start + Duration::from_secs(240)).len(), 1);
+        for _ in 0..10 {
+            poll.finished("INBOX", false, start);
+        }
+        assert_eq!(poll.due(start + Duration::from_secs(3600)).len(), 1);
+
+        // A rejected login holds every folder until a sync succeeds.
+        poll.auth_failed = true;
+        assert!(poll.due(start + Duration::from_secs(3600)).is_empty());
+        poll.finished("INBOX", true, start);
+        assert!(!poll.auth_failed);
+        assert_eq!(poll.due(later).len(), 1);
+    }
 
     #[test]
     fn imap_error_context_label_formats_fetch_body() {
@@ -328,4 +1015,15 @@ mod tests {
         });
         assert_eq!(label, "delete-messages Spam (2)");
     }
+
+    #[test]
+    fn imap_error_context_label_formats_set_flag() {
+        let label = imap_error_context_label(&ImapErrorContext::SetFlag {
+            message_id: 7,
+            folder_name: "INBOX".to_string(),
+            uid: 31,
+            seen: true,
+        });
+        assert_eq!(label, "set-flag seen id=7 folder=INBOX uid=31");
+    }
 }
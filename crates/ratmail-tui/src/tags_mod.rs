@@ -0,0 +1,148 @@
+use std::collections::BTreeSet;
+
+use ratatui::style::Color;
+use ratmail_mail::{is_valid_gmail_label, is_valid_imap_keyword};
+
+use super::{App, Mode};
+
+/// Keywords Thunderbird uses for its five default tags.
+const THUNDERBIRD_LABELS: [(&str, &str, Color); 5] = [
+    ("$label1", "Important", Color::Red),
+    ("$label2", "Work", Color::Yellow),
+    ("$label3", "Personal", Color::Green),
+    ("$label4", "To Do", Color::Blue),
+    ("$label5", "Later", Color::Magenta),
+];
+
+/// Bookkeeping keywords set by clients and spam filters; stored but not shown.
+const HIDDEN_KEYWORDS: [&str; 3] = ["$MDNSent", "$NotJunk", "NonJunk"];
+
+const CHIP_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightMagenta,
+    Color::LightBlue,
+    Color::LightRed,
+];
+
+fn thunderbird_label(keyword: &str) -> Option<(&'static str, Color)> {
+    THUNDERBIRD_LABELS
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(keyword))
+        .map(|(_, label, color)| (*label, *color))
+}
+
+pub(crate) fn keyword_hidden(keyword: &str) -> bool {
+    HIDDEN_KEYWORDS
+        .iter()
+        .any(|hidden| hidden.eq_ignore_ascii_case(keyword))
+}
+
+/// Display name for a keyword: Thunderbird's tag names, otherwise the keyword
+/// without its `$` prefix.
+pub(crate) fn keyword_label(keyword: &str) -> String {
+    match thunderbird_label(keyword) {
+        Some((label, _)) => label.to_string(),
+        None => keyword.strip_prefix('$').unwrap_or(keyword).to_string(),
+    }
+}
+
+/// Stable chip color, so a keyword looks the same in every row and session.
+pub(crate) fn keyword_color(keyword: &str) -> Color {
+    if let Some((_, color)) = thunderbird_label(keyword) {
+        return color;
+    }
+    let hash = keyword
+        .to_ascii_lowercase()
+        .bytes()
+        .fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
+    CHIP_COLORS[hash as usize % CHIP_COLORS.len()]
+}
+
+impl App {
+    pub(crate) fn open_tags_overlay(&mut self, ids: Vec<i64>, return_mode: Mode) {
+        if ids.is_empty() {
+            return;
+        }
+        self.bulk_action_ids = ids;
+        if return_mode != Mode::OverlayBulkAction {
+            self.bulk_done_return = return_mode;
+        }
+        self.tag_input.clear();
+        self.tag_index = 0;
+        self.overlay_return = return_mode;
+        self.mode = Mode::OverlayTags;
+    }
+
+    /// Keywords offered in the tag overlay: Thunderbird's defaults plus every
+    /// visible keyword already used in this account.
+    pub(crate) fn tag_choices(&self) -> Vec<String> {
+        let mut choices: BTreeSet<String> = THUNDERBIRD_LABELS
+            .iter()
+            .map(|(name, _, _)| name.to_string())
+            .collect();
+        for message in &self.store.messages {
+            choices.extend(
+                message
+                    .keywords
+                    .iter()
+                    .filter(|k| !keyword_hidden(k))
+                    .cloned(),
+            );
+        }
+        choices.into_iter().collect()
+    }
+
+    /// How many of the overlay's messages carry `keyword`.
+    pub(crate) fn tag_count(&self, keyword: &str) -> usize {
+        self.store
+            .messages
+            .iter()
+            .filter(|m| self.bulk_action_ids.contains(&m.id))
+            .filter(|m| m.keywords.iter().any(|k| k == keyword))
+            .count()
+    }
+
+    /// Removes the highlighted keyword if every message has it, else adds it.
+    pub(crate) fn toggle_selected_tag(&mut self) {
+        let Some(keyword) = self.tag_choices().get(self.tag_index).cloned() else {
+            return;
+        };
+        let ids = self.bulk_action_ids.clone();
+        if self.tag_count(&keyword) == ids.len() {
+            self.queue_update_keywords(ids, Vec::new(), vec![keyword]);
+        } else {
+            self.queue_update_keywords(ids, vec![keyword], Vec::new());
+        }
+    }
+
+    /// Adds the keywords typed into the overlay: space-separated, or
+    /// comma-separated Gmail labels, which may contain spaces.
+    pub(crate) fn submit_tag_input(&mut self) {
+        let (keywords, valid): (Vec<String>, fn(&str) -> bool) = if self.gmail_labels {
+            let labels = self
+                .tag_input
+                .split(',')
+                .map(str::trim)
+                .filter(|label| !label.is_empty())
+                .map(str::to_string)
+                .collect();
+            (labels, is_valid_gmail_label)
+        } else {
+            let keywords = self
+                .tag_input
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            (keywords, is_valid_imap_keyword)
+        };
+        if let Some(invalid) = keywords.iter().find(|k| !valid(k)) {
+            self.set_status(format!("Invalid tag: {}", invalid));
+            return;
+        }
+        self.tag_input.clear();
+        let ids = self.bulk_action_ids.clone();
+        self.queue_update_keywords(ids, keywords, Vec::new());
+    }
+}
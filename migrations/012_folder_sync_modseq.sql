@@ -0,0 +1 @@
+ALTER TABLE folder_sync_state ADD COLUMN highest_modseq INTEGER;
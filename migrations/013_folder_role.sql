@@ -0,0 +1,7 @@
+ALTER TABLE folders ADD COLUMN role TEXT;
+
+UPDATE folders SET role = 'drafts' WHERE name = 'Drafts';
+UPDATE folders SET role = 'sent' WHERE name = 'Sent';
+UPDATE folders SET role = 'trash' WHERE name = 'Trash';
+UPDATE folders SET role = 'archive' WHERE name = 'Archive';
+UPDATE folders SET role = 'junk' WHERE name = 'Spam';
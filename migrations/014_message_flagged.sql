@@ -0,0 +1 @@
+ALTER TABLE messages ADD COLUMN flagged INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+CREATE TABLE message_keywords (
+    message_id INTEGER NOT NULL,
+    keyword TEXT NOT NULL,
+    PRIMARY KEY (message_id, keyword),
+    FOREIGN KEY (message_id) REFERENCES messages(id)
+);
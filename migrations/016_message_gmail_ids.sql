@@ -0,0 +1,3 @@
+ALTER TABLE messages ADD COLUMN gm_msgid INTEGER;
+ALTER TABLE messages ADD COLUMN gm_thrid INTEGER;
+CREATE INDEX messages_gm_msgid_idx ON messages(account_id, gm_msgid);
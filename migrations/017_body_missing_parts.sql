@@ -0,0 +1,6 @@
+CREATE TABLE body_missing_parts (
+    message_id INTEGER NOT NULL,
+    section TEXT NOT NULL,
+    PRIMARY KEY (message_id, section),
+    FOREIGN KEY (message_id) REFERENCES messages(id)
+);
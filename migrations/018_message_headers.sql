@@ -0,0 +1,6 @@
+ALTER TABLE messages ADD COLUMN message_id_header TEXT NOT NULL DEFAULT '';
+ALTER TABLE messages ADD COLUMN in_reply_to TEXT NOT NULL DEFAULT '';
+ALTER TABLE messages ADD COLUMN references_header TEXT NOT NULL DEFAULT '';
+ALTER TABLE messages ADD COLUMN reply_to TEXT NOT NULL DEFAULT '';
+ALTER TABLE messages ADD COLUMN list_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE messages ADD COLUMN size INTEGER;
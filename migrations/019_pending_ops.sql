@@ -0,0 +1,12 @@
+CREATE TABLE pending_ops (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_ts INTEGER NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    last_attempt_ts INTEGER,
+    FOREIGN KEY (account_id) REFERENCES accounts(id)
+);
+CREATE INDEX pending_ops_account_idx ON pending_ops(account_id, id);
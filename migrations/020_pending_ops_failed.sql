@@ -0,0 +1,2 @@
+ALTER TABLE pending_ops ADD COLUMN failed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE pending_ops ADD COLUMN draft_id INTEGER;
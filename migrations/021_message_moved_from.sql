@@ -0,0 +1,2 @@
+ALTER TABLE messages ADD COLUMN moved_from_folder_id INTEGER;
+ALTER TABLE messages ADD COLUMN moved_from_uid INTEGER;